@@ -0,0 +1,170 @@
+/// Tag jots automatically based on rules in the config.
+use crate::jot::{find_tags, TAG_RE};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Tag any jot matching the pattern, or written in the directory. When both
+/// are given the jot has to match both.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagRule {
+    /// A regex matched against the message.
+    pub pattern: Option<String>,
+    /// Jots created in this directory (or below it) get tagged.
+    pub directory: Option<PathBuf>,
+    /// The tags to add, the leading `@` is optional.
+    pub tags: Vec<String>,
+}
+
+/// A tagging rule from the config, ready to be matched against jots.
+struct CompiledRule {
+    pattern: Option<Regex>,
+    directory: Option<PathBuf>,
+    tags: Vec<String>,
+}
+
+/// Applies every configured tagging rule to a jot's message.
+pub struct AutoTagger {
+    rules: Vec<CompiledRule>,
+}
+
+impl AutoTagger {
+    /// Compile the rules, failing if any of them are invalid.
+    pub fn new(rules: &[TagRule]) -> Result<AutoTagger> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                if rule.pattern.is_none() && rule.directory.is_none() {
+                    bail!(
+                        "the tag rule for {:?} needs a pattern or a directory",
+                        rule.tags
+                    );
+                }
+                let pattern = rule
+                    .pattern
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .with_context(|| {
+                        format!("invalid pattern in the tag rule for {:?}", rule.tags)
+                    })?;
+                let tags = rule
+                    .tags
+                    .iter()
+                    .map(|tag| normalize_tag(tag))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(CompiledRule {
+                    pattern,
+                    directory: rule.directory.as_deref().map(expand_home),
+                    tags,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(AutoTagger { rules })
+    }
+
+    /// The tags the rules want on this message that it doesn't have yet.
+    /// Directory rules only apply when we know the directory the jot was
+    /// written in, so pass `None` when going back over old jots.
+    pub fn missing_tags(&self, message: &str, cwd: Option<&Path>) -> Vec<String> {
+        let existing = find_tags(message);
+        let mut missing: Vec<String> = vec![];
+        for rule in &self.rules {
+            let pattern_matches = rule
+                .pattern
+                .as_ref()
+                .map(|pattern| pattern.is_match(message))
+                .unwrap_or(true);
+            let directory_matches = match (&rule.directory, cwd) {
+                (Some(directory), Some(cwd)) => cwd.starts_with(directory),
+                (Some(_), None) => false,
+                (None, _) => true,
+            };
+            if !(pattern_matches && directory_matches) {
+                continue;
+            }
+            for tag in &rule.tags {
+                if !existing.contains(tag) && !missing.contains(tag) {
+                    missing.push(tag.clone());
+                }
+            }
+        }
+        missing
+    }
+
+    /// Add any missing tags on a line of their own at the end of the message,
+    /// so they don't end up inside a checklist item.
+    pub fn apply(&self, message: &str, cwd: Option<&Path>) -> String {
+        let missing = self.missing_tags(message, cwd);
+        if missing.is_empty() {
+            message.to_string()
+        } else {
+            format!("{}\n{}", message.trim_end(), missing.join(" "))
+        }
+    }
+}
+
+/// Tags in the config or on the command line can be written with or without
+/// the leading `@`.
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = if tag.starts_with('@') {
+        tag.to_string()
+    } else {
+        format!("@{}", tag)
+    };
+    match TAG_RE.find(&tag) {
+        Some(found) if found.as_str() == tag => Ok(tag),
+        _ => bail!("{:?} is not a valid tag", tag),
+    }
+}
+
+/// Let directories in the config start with `~`.
+fn expand_home(directory: &Path) -> PathBuf {
+    match (directory.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => directory.to_path_buf(),
+    }
+}
+
+#[test]
+fn test_auto_tagger() {
+    let tagger = AutoTagger::new(&[
+        TagRule {
+            pattern: Some(r"\b[A-Z]+-\d+\b".to_string()),
+            directory: None,
+            tags: vec!["tickets".to_string()],
+        },
+        TagRule {
+            pattern: None,
+            directory: Some(PathBuf::from("/src/payments")),
+            tags: vec!["@payments".to_string(), "@work".to_string()],
+        },
+    ])
+    .unwrap();
+
+    assert_eq!(
+        tagger.apply("look at PAY-12\n", None),
+        "look at PAY-12\n@tickets"
+    );
+    assert_eq!(tagger.apply("PAY-12 @tickets", None), "PAY-12 @tickets");
+    assert_eq!(
+        tagger.apply("refunds @work", Some(Path::new("/src/payments/api"))),
+        "refunds @work\n@payments"
+    );
+    assert_eq!(
+        tagger.apply("PAY-12\n- [ ] refund it", None),
+        "PAY-12\n- [ ] refund it\n@tickets"
+    );
+    assert_eq!(
+        tagger.apply("refunds", Some(Path::new("/src/other"))),
+        "refunds"
+    );
+
+    assert!(AutoTagger::new(&[TagRule {
+        pattern: Some("x".to_string()),
+        directory: None,
+        tags: vec!["not a tag".to_string()],
+    }])
+    .is_err());
+}
@@ -0,0 +1,79 @@
+/// Markdown style checklists (`- [ ] thing`) inside of jot messages.
+use regex::Regex;
+
+lazy_static! {
+    static ref ITEM_RE: Regex = Regex::new(r"^(\s*[-*+] \[)([ xX])(\] .*)$").unwrap();
+}
+
+/// Go through each checklist item in the message, returning if it is checked.
+pub fn items(message: &str) -> Vec<bool> {
+    message
+        .lines()
+        .filter_map(|line| ITEM_RE.captures(line))
+        .map(|caps| &caps[2] != " ")
+        .collect()
+}
+
+/// How many items are checked off out of the total, if there is a checklist at all.
+pub fn progress(message: &str) -> Option<(usize, usize)> {
+    let items = items(message);
+    if items.is_empty() {
+        None
+    } else {
+        Some((
+            items.iter().filter(|checked| **checked).count(),
+            items.len(),
+        ))
+    }
+}
+
+/// Check off the nth (starting at 1) item in the checklist, returning the updated message.
+pub fn check(message: &str, n: usize) -> Option<String> {
+    let mut seen = 0;
+    let mut found = false;
+    let lines = message
+        .lines()
+        .map(|line| match ITEM_RE.captures(line) {
+            Some(caps) => {
+                seen += 1;
+                if seen == n {
+                    found = true;
+                    format!("{}x{}", &caps[1], &caps[3])
+                } else {
+                    line.to_string()
+                }
+            }
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    if found {
+        Some(lines.join("\n"))
+    } else {
+        None
+    }
+}
+
+/// Uncheck every item in the checklist.
+pub fn reset(message: &str) -> String {
+    message
+        .lines()
+        .map(|line| ITEM_RE.replace(line, "$1 $3").to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn test_checklist() {
+    let message = "deploy @ops\n- [ ] build\n- [x] test\n  * [X] nested\nnot - [ ] an item";
+    assert_eq!(items(message), vec![false, true, true]);
+    assert_eq!(progress(message), Some((2, 3)));
+    assert_eq!(progress("no items here"), None);
+
+    let checked = check(message, 1).unwrap();
+    assert_eq!(progress(&checked), Some((3, 3)));
+    assert!(checked.starts_with("deploy @ops\n- [x] build\n"));
+    assert_eq!(check(message, 4), None);
+
+    assert_eq!(progress(&reset(&checked)), Some((0, 3)));
+}
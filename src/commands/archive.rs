@@ -0,0 +1,167 @@
+/// Commands for putting old jots away in the archive live here.
+use crate::autotag::normalize_tag;
+use crate::config::{archive_path, Config, RetentionRule};
+use crate::constants::*;
+use crate::jot::{stream_jots, Jot, MessageType};
+use crate::output;
+use crate::utils::parse_age;
+use anyhow::Result;
+use chrono::prelude::*;
+use chrono::Duration;
+use colorful::Colorful;
+
+use crate::commands::edit::{confirm_changes, select_jots, Selection};
+
+/// Move the selected jots into the archive.
+pub fn archive_command(config: Config, selection: Selection) -> Result<()> {
+    let jots = select_jots(config.clone(), &selection)?;
+    if !confirm_changes(&jots, ARCHIVE, &selection, true)? {
+        return Ok(());
+    }
+    move_jots(&config, jots, true)
+}
+
+/// Bring the selected jots back out of the archive.
+pub fn unarchive_command(config: Config, selection: Selection) -> Result<()> {
+    let jots = select_jots(config.clone(), &selection)?;
+    if !confirm_changes(&jots, "unarchive", &selection, true)? {
+        return Ok(());
+    }
+    move_jots(&config, jots, false)
+}
+
+/// Archive everything the retention rules in the config say is old enough.
+/// Pinned jots are left alone.
+pub fn auto_archive_command(config: Config, dry_run: bool) -> Result<()> {
+    let now: DateTime<Local> = Local::now();
+    let rules = config
+        .retention
+        .iter()
+        .map(|rule| Ok((rule, parse_age(&rule.older_than)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let jots = stream_jots(config.clone(), false)?
+        .filter(|jot| !jot.pinned)
+        .filter(|jot| {
+            rules
+                .iter()
+                .any(|(rule, age)| rule_matches(rule, *age, jot, now))
+        })
+        .collect::<Vec<_>>();
+
+    if dry_run {
+        for jot in &jots {
+            jot.pprint();
+            println!();
+        }
+        println!("{} jots would be archived", jots.len());
+        return Ok(());
+    }
+    move_jots(&config, jots, true)
+}
+
+/// Whether a retention rule says this jot should be archived.
+fn rule_matches(rule: &RetentionRule, age: Duration, jot: &Jot, now: DateTime<Local>) -> bool {
+    let (type_name, state, finished) = match &jot.msg_type {
+        MessageType::Note => (NOTE, None, None),
+        MessageType::Todo(todo) => (
+            TODO,
+            Some(todo.state.name()),
+            todo.state.since().filter(|_| todo.state.is_finished()),
+        ),
+        MessageType::Day(_) => (DAY, None, None),
+        MessageType::Custom(jot_type) => (jot_type.name.as_str(), None, None),
+    };
+
+    let tagged = rule.tags.iter().all(|tag| match normalize_tag(tag) {
+        Ok(tag) => jot.tags.contains(&tag),
+        Err(_) => false,
+    });
+
+    rule.jot_type
+        .as_deref()
+        .map(|t| t == type_name)
+        .unwrap_or(true)
+        && rule
+            .state
+            .as_deref()
+            .map(|s| Some(s) == state)
+            .unwrap_or(true)
+        && tagged
+        && now - finished.unwrap_or(jot.datetime) > age
+}
+
+#[test]
+fn test_rule_matches() {
+    use crate::jot::{Todo, TodoState};
+    use std::collections::HashSet;
+
+    let now = Local.ymd(2020, 6, 1).and_hms(12, 0, 0);
+    let rule = RetentionRule {
+        older_than: "90 days".to_string(),
+        jot_type: Some(TODO.to_string()),
+        state: Some(TODO_STATE_DONE.to_string()),
+        tags: vec![],
+    };
+    let age = parse_age(&rule.older_than).unwrap();
+
+    let todo = |state| {
+        Jot::new(
+            std::path::Path::new("00000000000000.jot"),
+            Local.ymd(2019, 1, 1).and_hms(12, 0, 0),
+            "old todo @work",
+            MessageType::Todo(Todo {
+                state,
+                ..Todo::default()
+            }),
+            &HashSet::new(),
+        )
+    };
+    // Finished todos are aged from when they were finished.
+    assert!(rule_matches(
+        &rule,
+        age,
+        &todo(TodoState::Done(Local.ymd(2020, 1, 1).and_hms(0, 0, 0))),
+        now
+    ));
+    assert!(!rule_matches(
+        &rule,
+        age,
+        &todo(TodoState::Done(Local.ymd(2020, 5, 1).and_hms(0, 0, 0))),
+        now
+    ));
+    assert!(!rule_matches(&rule, age, &todo(TodoState::Open), now));
+
+    let tagged = RetentionRule {
+        jot_type: None,
+        state: None,
+        tags: vec!["work".to_string()],
+        ..rule.clone()
+    };
+    assert!(rule_matches(&tagged, age, &todo(TodoState::Open), now));
+}
+
+/// Move jots between the journal and the archive, keeping their file names.
+fn move_jots(config: &Config, jots: Vec<Jot>, to_archive: bool) -> Result<()> {
+    let (to, action) = if to_archive {
+        (archive_path(config), "archived")
+    } else {
+        (config.journal_path.clone(), "unarchived")
+    };
+    std::fs::create_dir_all(&to)?;
+
+    for jot in &jots {
+        if let Some(file_name) = jot.path.file_name() {
+            std::fs::rename(&jot.path, to.join(file_name))?;
+        }
+        println!(
+            "{}",
+            output::paint(&format!(
+                "{} [{}] {}",
+                action.dim(),
+                jot.uuid.clone().unwrap_or_else(|| jot.id.to_string()),
+                jot.message.lines().next().unwrap_or("")
+            ))
+        );
+    }
+    Ok(())
+}
@@ -1,61 +1,150 @@
 /// Commands for creating new notes/todos/reminders.
-use crate::config::Config;
-use crate::jot::{Jot, MessageType};
-use anyhow::Result;
+use crate::autotag::AutoTagger;
+use crate::config::{archive_path, Config, JotType};
+use crate::jot::{Jot, MessageType, Priority, Todo, TodoState};
+use crate::recurrence::Recurrence;
+use anyhow::{bail, Result};
+use chrono::prelude::*;
 use std::collections::HashSet;
-use std::io::Write;
-
-/// Get input from the users default $EDITOR.
-/// If the input is empty or all whitespace we will
-/// kill the process.
-fn get_user_input() -> Result<String> {
-    let message = scrawl::new()?;
-    if message.trim().is_empty() {
+use std::io::prelude::*;
+
+use crate::commands::tags::check_tag_typos;
+
+/// How the user asked for a new jot to be captured.
+#[derive(Debug, Default)]
+pub struct CaptureOptions {
+    /// The message given on the command line, `-` means read it from stdin.
+    pub message: Option<String>,
+    /// Only print the uuid of the new jot rather than the whole thing.
+    pub quiet: bool,
+    /// A rendered template to start the jot from.
+    pub template: Option<String>,
+    /// When the jot happened if it wasn't just now.
+    pub at: Option<DateTime<Local>>,
+}
+
+/// Get input from the command line, stdin or failing that the users default $EDITOR.
+/// If the input is empty or all whitespace it's an error, unless it came from
+/// the editor where that's how you give up, then we quietly kill the process.
+fn get_user_input(options: &CaptureOptions) -> Result<String> {
+    let template = options.template.as_deref().unwrap_or("");
+    let message = match options.message.as_deref() {
+        Some("-") => {
+            let mut message = String::new();
+            std::io::stdin().read_to_string(&mut message)?;
+            message
+        }
+        Some(message) => message.to_string(),
+        None => scrawl::with(template)?,
+    };
+
+    if options.message.is_some() && message.trim().is_empty() {
+        bail!("there's nothing to write, the message is empty");
+    }
+    // Saving an untouched template counts as giving up.
+    if message.trim().is_empty() || message.trim() == template.trim() {
         std::process::exit(0)
     }
-    return Ok(message);
+
+    if options.message.is_some() && !template.trim().is_empty() {
+        return Ok(format!("{}\n\n{}", template.trim(), message.trim()));
+    }
+    Ok(message)
 }
 
 /// Append a jot to the journal specified in the config.
-fn write_jot_to_file(jot: &Jot) -> Result<()> {
+pub fn write_jot_to_file(jot: &Jot) -> Result<()> {
     let mut file = std::fs::File::create(&jot.path)?;
     file.write_all(jot.to_string().as_bytes())?;
 
     Ok(())
 }
 
-pub fn create_note_command(config: Config, previous_uuids: &HashSet<String>) -> Result<()> {
-    let message = get_user_input()?;
+/// Capture a message and write out a new jot of the given type.
+fn create_jot(
+    config: Config,
+    previous_uuids: &HashSet<String>,
+    msg_type: MessageType,
+    options: CaptureOptions,
+) -> Result<()> {
+    let message = get_user_input(&options)?;
+    let message = AutoTagger::new(&config.tag_rules)?
+        .apply(message.trim(), std::env::current_dir().ok().as_deref());
+    // Only offer to fix typos when someone is there to answer.
+    let interactive =
+        console::user_attended() && !options.quiet && options.message.as_deref() != Some("-");
+    let message = check_tag_typos(config.clone(), &message, None, &HashSet::new(), interactive)?;
 
-    let path = compute_path(config)?;
-    let jot = Jot::new(&path, message.trim(), MessageType::Note, previous_uuids);
+    let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
+    let at = options.at.unwrap_or(now);
+    let path = compute_path(config, at)?;
+    let jot = Jot::new(&path, at, &message, msg_type, previous_uuids);
 
     write_jot_to_file(&jot)?;
-    jot.pprint();
+    if options.quiet {
+        println!("{}", jot.uuid.clone().unwrap_or_default());
+    } else {
+        jot.pprint();
+    }
     Ok(())
 }
 
-pub fn create_todo_command(config: Config, previous_uuids: &HashSet<String>) -> Result<()> {
-    let message = get_user_input()?;
+pub fn create_note_command(
+    config: Config,
+    previous_uuids: &HashSet<String>,
+    options: CaptureOptions,
+) -> Result<()> {
+    create_jot(config, previous_uuids, MessageType::Note, options)
+}
 
-    let path = compute_path(config)?;
-    let jot = Jot::new(
-        &path,
-        message.trim(),
-        MessageType::Todo(None),
+/// Create one of the types defined in the config.
+pub fn create_custom_command(
+    config: Config,
+    previous_uuids: &HashSet<String>,
+    jot_type: JotType,
+    options: CaptureOptions,
+) -> Result<()> {
+    create_jot(
+        config,
         previous_uuids,
-    );
-
-    write_jot_to_file(&jot)?;
-    jot.pprint();
-    Ok(())
+        MessageType::Custom(jot_type),
+        options,
+    )
 }
 
-fn compute_path(config: Config) -> Result<std::path::PathBuf> {
-    let now = std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH)?;
-    let fname = format!("{:0>14}.jot", now.as_secs());
+pub fn create_todo_command(
+    config: Config,
+    previous_uuids: &HashSet<String>,
+    due: Option<DateTime<Local>>,
+    priority: Option<Priority>,
+    recurrence: Option<Recurrence>,
+    options: CaptureOptions,
+) -> Result<()> {
+    let todo = Todo {
+        state: TodoState::Open,
+        due,
+        priority,
+        recurrence,
+        worked: vec![],
+        history: vec![],
+    };
+    create_jot(config, previous_uuids, MessageType::Todo(todo), options)
+}
 
-    let mut jot_path = config.journal_path.clone();
-    jot_path.push(fname);
-    Ok(jot_path)
+/// Figure out the file for a jot written at the given time, the journal is
+/// ordered by file name so this keeps backdated jots in the right spot.
+pub fn compute_path(config: Config, at: DateTime<Local>) -> Result<std::path::PathBuf> {
+    // Files are ordered by their name, if we already wrote a jot this second
+    // bump it forward a bit rather than clobbering it. Names are kept unique
+    // with the archive too so jots can move back and forth.
+    let archive_path = archive_path(&config);
+    let mut secs = std::cmp::max(at.timestamp(), 0);
+    loop {
+        let file_name = format!("{:0>14}.jot", secs);
+        let jot_path = config.journal_path.join(&file_name);
+        if !jot_path.exists() && !archive_path.join(&file_name).exists() {
+            return Ok(jot_path);
+        }
+        secs += 1;
+    }
 }
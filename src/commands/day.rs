@@ -0,0 +1,65 @@
+/// Commands for keeping a daily journal live here.
+use crate::config::Config;
+use crate::jot::{stream_jots, Jot, MessageType};
+use crate::utils::parse_human_range;
+use anyhow::Result;
+use chrono::prelude::*;
+use std::collections::HashSet;
+
+use crate::commands::create::{compute_path, write_jot_to_file};
+
+/// Open the daily entry for the given human date (e.g. "today" or "last friday"),
+/// creating it if it doesn't exist yet. Each time we add a new timestamped section.
+pub fn day_command(config: Config, date: &str, previous_uuids: &HashSet<String>) -> Result<()> {
+    let (start, _end) = parse_human_range(date)?;
+    let date = start.date().naive_local();
+
+    let existing = stream_jots(config.clone(), false)?.find(|jot| match jot.msg_type {
+        MessageType::Day(day) => day == date,
+        _ => false,
+    });
+
+    let previous_message = existing
+        .as_ref()
+        .map(|jot| jot.message.trim().to_string())
+        .unwrap_or_default();
+    let section = format!("## {}\n\n", Local::now().format("%H:%M"));
+    let template = if previous_message.is_empty() {
+        section.clone()
+    } else {
+        format!("{}\n\n{}", previous_message, section)
+    };
+
+    let message = scrawl::with(&template)?;
+
+    // If nothing was written in the new section leave the day alone.
+    if message.trim() == template.trim() || message.trim() == previous_message {
+        return Ok(());
+    }
+
+    let jot = match existing {
+        Some(mut jot) => {
+            jot.message = message.trim().to_string();
+            jot
+        }
+        None => {
+            // File the day under its own date, not whenever we happened to write it.
+            let at = Local
+                .from_local_datetime(&date.and_hms(0, 0, 0))
+                .earliest()
+                .unwrap();
+            Jot::new(
+                &compute_path(config, at)?,
+                at,
+                message.trim(),
+                MessageType::Day(date),
+                previous_uuids,
+            )
+        }
+    };
+
+    // The day already exists on disk, so this overwrites it.
+    write_jot_to_file(&jot)?;
+    jot.pprint();
+    Ok(())
+}
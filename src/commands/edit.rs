@@ -1,9 +1,22 @@
 /// Commands that modify the journal (other than appending) live here.
+use crate::autotag::AutoTagger;
+use crate::checklist;
 use crate::config::Config;
-use crate::jot::{stream_jots, Jot, MessageType};
-use anyhow::Result;
+use crate::constants::*;
+use crate::jot::{
+    check_header, parse_jot_contents, stream_all_jots, stream_jots, Jot, MessageType, Todo,
+    TodoState, WorkInterval,
+};
+use crate::output;
+use crate::utils::{confirm, edit_distance};
+use anyhow::{anyhow, bail, Result};
 use chrono::prelude::*;
-use std::io::Write;
+use colorful::Colorful;
+use std::io::{Read, Write};
+
+use crate::commands::create::{compute_path, write_jot_to_file};
+use crate::commands::tags::check_tag_typos;
+use crate::commands::view::{get_all_uuids, Query};
 
 fn update_jot(jot: &Jot) -> Result<()> {
     // We are in directory mode so just overwrite that specific file.
@@ -11,85 +24,566 @@ fn update_jot(jot: &Jot) -> Result<()> {
     let mut file = std::fs::OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(&jot.path)?;
 
     file.write_all(jot.to_string().as_bytes())?;
-    return Ok(());
+    Ok(())
 }
 
-pub fn mark_todo_complete_command(config: Config, note_id_to_mark_complete: &str) -> Result<()> {
-    // If the user passed in a number we're checking the count (id) not the uuid.
-    let maybe_check_id = note_id_to_mark_complete.parse::<usize>().ok();
-    let uuid = Some(note_id_to_mark_complete.to_string());
+/// Which jots a bulk command should change, any listed ids that also match
+/// the view filters.
+pub struct Selection<'a> {
+    pub ids: Vec<&'a str>,
+    pub query: Query,
+    /// Skip the preview and confirmation.
+    pub yes: bool,
+    /// Pick from the archived jots rather than the journal.
+    pub archived: bool,
+}
 
-    // TODO: if we didn't find the id/uuid let the user know.
+/// Find every jot the selection picks out.
+pub fn select_jots(config: Config, selection: &Selection) -> Result<Vec<Jot>> {
+    let jots = stream_all_jots(config, false, selection.archived)?
+        .filter(|jot| jot.archived == selection.archived)
+        .collect::<Vec<_>>();
+    for id_or_uuid in &selection.ids {
+        if !jots.iter().any(|jot| is_jot(jot, id_or_uuid)) {
+            return Err(not_found(&jots, id_or_uuid));
+        }
+    }
 
-    // Read in the entire file Jot file and stream them to a temp file.
+    Ok(jots
+        .into_iter()
+        .filter(|jot| selection.ids.is_empty() || selection.ids.iter().any(|id| is_jot(jot, id)))
+        .filter(|jot| selection.query.matches(jot))
+        .collect())
+}
 
-    let found_jot = stream_jots(config.clone(), false)?
-        .find(|jot| jot.uuid == uuid || Some(jot.id) == maybe_check_id);
+/// Show what is about to change and ask before going ahead. A single jot
+/// picked by its id is changed right away like it always has been, unless
+/// the change can't be undone. Saying no (or having nobody there to answer)
+/// is an error so scripts don't think it worked.
+pub fn confirm_changes(
+    jots: &[Jot],
+    action: &str,
+    selection: &Selection,
+    undoable: bool,
+) -> Result<bool> {
+    if jots.is_empty() {
+        println!("no jots matched");
+        return Ok(false);
+    }
+    if selection.yes || (undoable && jots.len() == 1 && selection.query.is_empty()) {
+        return Ok(true);
+    }
 
-    if let Some(mut jot) = found_jot {
-        match jot.msg_type {
-            MessageType::Todo(_) => {
-                let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
-                jot.msg_type = MessageType::Todo(Some(now));
-                jot.pprint();
-                return update_jot(&jot);
-            }
+    for jot in jots {
+        jot.pprint();
+        println!();
+    }
+    if !confirm(&format!(
+        "{} {} {}?",
+        action,
+        jots.len(),
+        if jots.len() == 1 { "jot" } else { "jots" }
+    ))? {
+        bail!(
+            "didn't {} anything, pass --yes to {} without being asked",
+            action,
+            action
+        );
+    }
+    Ok(true)
+}
 
-            _ => {
-                println!("you can only complete a todo");
-                std::process::exit(1)
+pub fn mark_todo_complete_command(config: Config, selection: Selection) -> Result<()> {
+    let mut jots = select_jots(config.clone(), &selection)?;
+    let is_todo = |jot: &Jot| matches!(jot.msg_type, MessageType::Todo(_));
+    if !selection.ids.is_empty() && !jots.iter().all(is_todo) {
+        println!("you can only complete a todo");
+        std::process::exit(1)
+    }
+    if !selection.ids.is_empty() {
+        for jot in &jots {
+            if let MessageType::Todo(todo) = &jot.msg_type {
+                if todo.state.is_finished() {
+                    bail!(
+                        "[{}] is already {}, reopen it first",
+                        jot.uuid.clone().unwrap_or_else(|| jot.id.to_string()),
+                        todo.state.name()
+                    );
+                }
             }
         }
     }
+    // Filters will pick up plenty of other jots, only keep the open todos.
+    if !selection.query.is_empty() {
+        jots.retain(|jot| match &jot.msg_type {
+            MessageType::Todo(todo) => !todo.state.is_finished(),
+            _ => false,
+        });
+    }
+
+    if !confirm_changes(&jots, "complete", &selection, true)? {
+        return Ok(());
+    }
+    for jot in jots {
+        complete_todo(config.clone(), jot)?;
+    }
+    Ok(())
+}
+
+/// Mark a todo as complete, if it recurs we will also create the next one.
+fn complete_todo(config: Config, mut jot: Jot) -> Result<()> {
+    let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
+    let todo = match jot.msg_type {
+        // Finishing it again would spawn another copy of a recurring todo.
+        MessageType::Todo(ref todo) if todo.state.is_finished() => return Ok(()),
+        MessageType::Todo(ref mut todo) => {
+            todo.set_state(TodoState::Done(now), now);
+            stop_working(todo, now);
+            todo.clone()
+        }
+        _ => return Ok(()),
+    };
+    jot.pprint();
+    update_jot(&jot)?;
+
+    if let Some(recurrence) = todo.recurrence {
+        let next_due = match todo.due {
+            Some(due) => recurrence.next_due(due),
+            // Without a due date we count from today, due by the end of the day.
+            None => {
+                let next_day = recurrence.next_date(Local::today().naive_local());
+                Local
+                    .from_local_datetime(&next_day.succ().and_hms(0, 0, 0))
+                    .earliest()
+                    .unwrap()
+            }
+        };
+
+        let next_todo = Todo {
+            state: TodoState::Open,
+            due: Some(next_due),
+            priority: todo.priority,
+            recurrence: todo.recurrence,
+            worked: vec![],
+            history: vec![],
+        };
+        let previous_uuids = get_all_uuids(config.clone())?;
+        let next_jot = Jot::new(
+            &compute_path(config, now)?,
+            now,
+            &checklist::reset(&jot.message),
+            MessageType::Todo(next_todo),
+            &previous_uuids,
+        );
+        write_jot_to_file(&next_jot)?;
+        next_jot.pprint();
+    }
 
-    // TODO: error couldn't find it.
     Ok(())
 }
 
-pub fn delete_jot(config: Config, note_id_to_delete: &str) -> Result<()> {
+/// Whether the user meant this jot by the id or uuid they gave.
+fn is_jot(jot: &Jot, id_or_uuid: &str) -> bool {
     // If the user passed in a number we're checking the count (id) not the uuid.
-    let maybe_check_id = note_id_to_delete.parse::<usize>().ok();
-    let uuid = Some(note_id_to_delete.to_string());
+    jot.uuid.as_deref() == Some(id_or_uuid)
+        || (jot.id != 0 && id_or_uuid.parse::<usize>().ok() == Some(jot.id))
+}
 
-    // TODO: if we didn't find the id/uuid let the user know.
+/// Find a jot by either its id or its uuid.
+fn find_jot(config: Config, id_or_uuid: &str) -> Result<Jot> {
+    let jots = stream_jots(config, false)?.collect::<Vec<_>>();
+    match jots.iter().position(|jot| is_jot(jot, id_or_uuid)) {
+        Some(i) => Ok(jots.into_iter().nth(i).unwrap()),
+        None => Err(not_found(&jots, id_or_uuid)),
+    }
+}
+
+/// An error for an id we couldn't find, pointing at the jots they probably
+/// meant: ones with a uuid a typo away, or failing that the latest ones.
+fn not_found(jots: &[Jot], id_or_uuid: &str) -> anyhow::Error {
+    let mut close = jots
+        .iter()
+        .rev()
+        .filter_map(|jot| {
+            let uuid = jot.uuid.as_deref()?;
+            let distance =
+                edit_distance(&uuid.to_ascii_lowercase(), &id_or_uuid.to_ascii_lowercase());
+            if distance <= 1 || (distance == 2 && uuid.len() > 3) {
+                Some((distance, jot))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    close.sort_by_key(|(distance, _)| *distance);
+
+    let (heading, suggestions) = if close.is_empty() {
+        (
+            "the latest jots are",
+            jots.iter().rev().take(3).collect::<Vec<_>>(),
+        )
+    } else {
+        (
+            "did you mean",
+            close.into_iter().map(|(_, jot)| jot).take(3).collect(),
+        )
+    };
+
+    let mut message = format!("couldn't find a jot with the id {:?}", id_or_uuid);
+    if !suggestions.is_empty() {
+        message.push_str(&format!(", {}:", heading));
+        for jot in suggestions {
+            message.push_str(&format!(
+                "\n  {:<4} {}",
+                jot.uuid.clone().unwrap_or_else(|| jot.id.to_string()),
+                jot.message.lines().next().unwrap_or("")
+            ));
+        }
+    }
+    anyhow!(message)
+}
 
-    for jot in stream_jots(config.clone(), false)? {
-        let this_one_should_be_deleted = jot.uuid == uuid || Some(jot.id) == maybe_check_id;
-        if this_one_should_be_deleted {
-            // Just delete the file and return.
-            std::fs::remove_file(&jot.path)?;
+/// Check off an item in a jot's checklist without opening the editor.
+pub fn check_item_command(config: Config, id_or_uuid: &str, item: usize) -> Result<()> {
+    let mut jot = find_jot(config.clone(), id_or_uuid)?;
+    match checklist::check(&jot.message, item) {
+        Some(message) => jot.message = message,
+        None => {
+            println!("there is no checklist item {}", item);
+            std::process::exit(1)
+        }
+    }
+
+    let all_checked = checklist::items(&jot.message)
+        .iter()
+        .all(|checked| *checked);
+    match &jot.msg_type {
+        MessageType::Todo(todo)
+            if !todo.state.is_finished() && all_checked && config.complete_finished_checklists =>
+        {
+            complete_todo(config, jot)
+        }
+        _ => {
             jot.pprint();
+            update_jot(&jot)
+        }
+    }
+}
+
+pub fn delete_command(config: Config, selection: Selection) -> Result<()> {
+    let jots = select_jots(config, &selection)?;
+    if !confirm_changes(&jots, "delete", &selection, false)? {
+        return Ok(());
+    }
+    for jot in jots {
+        std::fs::remove_file(&jot.path)?;
+        jot.pprint();
+    }
+    Ok(())
+}
+
+/// Open each of the selected jots in the editor one after another, with
+/// `full` the header can be edited along with the message.
+pub fn edit_command(config: Config, selection: Selection, full: bool) -> Result<()> {
+    let jots = select_jots(config.clone(), &selection)?;
+    if !confirm_changes(&jots, "edit", &selection, true)? {
+        return Ok(());
+    }
+    let tagger = AutoTagger::new(&config.tag_rules)?;
+    for jot in jots {
+        if full {
+            edit_full_jot(config.clone(), &tagger, jot)?;
+        } else {
+            edit_jot_contents(config.clone(), &tagger, jot)?;
+        }
+    }
+    Ok(())
+}
 
+/// Edit the header and the message together so the date, type, completion
+/// date or uuid can be fixed up. We keep going back to the editor until the
+/// result parses or the user gives up.
+fn edit_full_jot(config: Config, tagger: &AutoTagger, jot: Jot) -> Result<()> {
+    let mut contents = jot.to_string();
+    loop {
+        contents = scrawl::with(contents.trim())?;
+        if contents.trim().is_empty() {
             return Ok(());
         }
+
+        match parse_full_edit(config.clone(), &jot, &contents) {
+            Ok(edited) => return save_full_edit(config, tagger, jot, edited),
+            Err(err) => {
+                println!("{}", err);
+                if !confirm("edit it again?")? {
+                    bail!("nothing was saved");
+                }
+            }
+        }
+    }
+}
+
+/// Turn what came back from the editor into a jot, making sure nothing in the
+/// header gets lost along the way.
+fn parse_full_edit(config: Config, original: &Jot, contents: &str) -> Result<Jot> {
+    check_header(contents.lines().next().unwrap_or(""), &config.types)?;
+    let mut edited = parse_jot_contents(contents, &original.path, &config.types)
+        .ok_or_else(|| anyhow!("the jot no longer parses"))?;
+    edited.id = original.id;
+
+    if edited.uuid != original.uuid {
+        if let Some(uuid) = &edited.uuid {
+            if get_all_uuids(config)?.contains(uuid) {
+                bail!("another jot already has the id {}", uuid);
+            }
+        }
     }
+    Ok(edited)
+}
+
+fn save_full_edit(
+    config: Config,
+    tagger: &AutoTagger,
+    original: Jot,
+    mut edited: Jot,
+) -> Result<()> {
+    let message = tagger.apply(edited.message.trim(), None);
+    edited.message = check_tag_typos(
+        config.clone(),
+        &message,
+        Some(&original.path),
+        &original.tags,
+        console::user_attended(),
+    )?;
+
+    // The journal is ordered by file name so a new date means a new file.
+    if edited.datetime != original.datetime {
+        edited.path = compute_path(config, edited.datetime)?;
+        write_jot_to_file(&edited)?;
+        std::fs::remove_file(&original.path)?;
+    } else {
+        update_jot(&edited)?;
+    }
+    edited.pprint();
     Ok(())
 }
 
-pub fn edit_jot_contents(config: Config, note_id_to_edit: &str) -> Result<()> {
-    // If the user passed in a number we're checking the count (id) not the uuid.
-    let maybe_check_id = note_id_to_edit.parse::<usize>().ok();
-    let uuid = Some(note_id_to_edit.to_string());
+fn edit_jot_contents(config: Config, tagger: &AutoTagger, mut jot: Jot) -> Result<()> {
+    let message = scrawl::with(jot.message.trim()).unwrap();
 
-    let found_jot = stream_jots(config.clone(), false)?
-        .find(|jot| jot.uuid == uuid || Some(jot.id) == maybe_check_id);
+    if message.trim().is_empty() {
+        Ok(())
+    } else {
+        // We don't know where the jot was written so only the pattern
+        // rules apply here.
+        let message = tagger.apply(message.trim(), None);
+        jot.message = check_tag_typos(
+            config,
+            &message,
+            Some(&jot.path),
+            &jot.tags,
+            console::user_attended(),
+        )?;
+        jot.pprint();
 
-    if let Some(mut jot) = found_jot {
-        let message = scrawl::with(&jot.message.trim()).unwrap();
+        update_jot(&jot)
+    }
+}
 
-        if message.trim().is_empty() {
-            return Ok(());
-        } else {
-            jot.message = message;
+/// Clear the completion date of a todo so it shows up as not done again.
+pub fn reopen_todo_command(config: Config, id_or_uuid: &str) -> Result<()> {
+    let mut jot = find_jot(config, id_or_uuid)?;
+    match jot.msg_type {
+        MessageType::Todo(ref mut todo) => {
+            let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
+            todo.set_state(TodoState::Open, now);
+            jot.pprint();
+            update_jot(&jot)
+        }
+
+        _ => {
+            println!("you can only reopen a todo");
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Pin a jot so it's shown first, or unpin it.
+pub fn set_pinned_command(config: Config, id_or_uuid: &str, pinned: bool) -> Result<()> {
+    let mut jot = find_jot(config, id_or_uuid)?;
+    jot.pinned = pinned;
+    jot.pprint();
+    update_jot(&jot)
+}
+
+/// Turn a note into a todo or vice versa, everything other than the type stays the same.
+pub fn convert_jot_command(config: Config, id_or_uuid: &str, to: &str) -> Result<()> {
+    let custom_type = config
+        .types
+        .iter()
+        .find(|jot_type| jot_type.name == to)
+        .cloned();
+    let mut jot = find_jot(config, id_or_uuid)?;
+    jot.msg_type = match (&jot.msg_type, to) {
+        // It's already the type we want.
+        (MessageType::Todo(_), TODO) => jot.msg_type.clone(),
+        (_, TODO) => MessageType::Todo(Todo::default()),
+        (_, NOTE) => MessageType::Note,
+        (msg_type, _) => custom_type
+            .map(MessageType::Custom)
+            .unwrap_or_else(|| msg_type.clone()),
+    };
+    jot.pprint();
+    update_jot(&jot)
+}
+
+/// Move a todo into a new workflow state (in progress, blocked, waiting or cancelled).
+pub fn set_todo_state_command(
+    config: Config,
+    id_or_uuid: &str,
+    state: &str,
+    reason: Option<&str>,
+) -> Result<()> {
+    let mut jot = find_jot(config, id_or_uuid)?;
+    match jot.msg_type {
+        // Don't quietly throw away when it was completed or cancelled.
+        MessageType::Todo(ref todo) if todo.state.is_finished() => bail!(
+            "[{}] is already {}, reopen it first",
+            id_or_uuid,
+            todo.state.name()
+        ),
+        MessageType::Todo(ref mut todo) => {
+            let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
+            // Starting a todo starts the clock on it, anything else stops it.
+            if state == TODO_STATE_IN_PROGRESS {
+                if todo.running_interval().is_none() {
+                    todo.worked.push(WorkInterval {
+                        start: now,
+                        end: None,
+                    });
+                }
+            } else {
+                stop_working(todo, now);
+            }
+
+            let state = match state {
+                TODO_STATE_IN_PROGRESS => TodoState::InProgress(now),
+                TODO_STATE_BLOCKED => {
+                    TodoState::Blocked(now, reason.unwrap_or_default().trim().to_string())
+                }
+                TODO_STATE_WAITING => TodoState::Waiting(now),
+                TODO_STATE_CANCELLED => TodoState::Cancelled(now),
+                _ => unreachable!("unknown todo state {}", state),
+            };
+            todo.set_state(state, now);
+            jot.pprint();
+            update_jot(&jot)
+        }
+
+        _ => {
+            println!("only todos have a state");
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Close out the interval we're working in, if there is one.
+fn stop_working(todo: &mut Todo, now: DateTime<Local>) -> bool {
+    match todo.running_interval() {
+        Some(interval) => {
+            interval.end = Some(now);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stop the clock on a todo, it goes back to being open if it was in progress.
+pub fn stop_todo_command(config: Config, id_or_uuid: &str) -> Result<()> {
+    let mut jot = find_jot(config, id_or_uuid)?;
+    match jot.msg_type {
+        MessageType::Todo(ref mut todo) => {
+            let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
+            if !stop_working(todo, now) {
+                println!("you aren't working on that todo, use jot start first");
+                std::process::exit(1)
+            }
+            if let TodoState::InProgress(_) = todo.state {
+                todo.set_state(TodoState::Open, now);
+            }
             jot.pprint();
+            update_jot(&jot)
+        }
+
+        _ => {
+            println!("you can only track time on a todo");
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Add a timestamped line (or paragraph) to the end of a jot without opening
+/// the editor, `-` reads the text from stdin.
+pub fn append_command(config: Config, id_or_uuid: &str, text: &str) -> Result<()> {
+    let mut jot = find_jot(config.clone(), id_or_uuid)?;
+    let text = if text == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        text.to_string()
+    };
+    if text.trim().is_empty() {
+        bail!("there's nothing to append");
+    }
 
-            return update_jot(&jot);
+    let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
+    let message = format!(
+        "{}\n[{}] {}",
+        jot.message.trim_end(),
+        now.format("%Y-%m-%d %H:%M"),
+        text.trim()
+    );
+    // Appending is mostly done from scripts so only warn about tag typos.
+    let message = AutoTagger::new(&config.tag_rules)?.apply(&message, None);
+    jot.message = check_tag_typos(config, &message, Some(&jot.path), &jot.tags, false)?;
+    jot.pprint();
+    update_jot(&jot)
+}
+
+/// Go back over the journal and apply the tagging rules from the config.
+pub fn retag_command(config: Config, dry_run: bool) -> Result<()> {
+    let tagger = AutoTagger::new(&config.tag_rules)?;
+    let mut retagged = 0;
+    for mut jot in stream_jots(config, false)? {
+        let missing = tagger.missing_tags(&jot.message, None);
+        if missing.is_empty() {
+            continue;
+        }
+        retagged += 1;
+        println!(
+            "{}",
+            output::paint(&format!(
+                "{} [{}] {}",
+                missing.join(" ").bold(),
+                jot.uuid.clone().unwrap_or_else(|| jot.id.to_string()),
+                jot.message.lines().next().unwrap_or("")
+            ))
+        );
+        if !dry_run {
+            jot.message = tagger.apply(&jot.message, None);
+            update_jot(&jot)?;
         }
     }
 
-    // TODO jot not found error
+    if dry_run {
+        println!("{} jots would be retagged", retagged);
+    } else {
+        println!("retagged {} jots", retagged);
+    }
     Ok(())
 }
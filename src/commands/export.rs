@@ -0,0 +1,436 @@
+/// Commands for publishing the journal somewhere else live here.
+use crate::config::Config;
+use crate::constants::*;
+use crate::jot::{stream_all_jots, Jot, MessageType, TAG_RE};
+use anyhow::{bail, Context, Result};
+use chrono::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::view::Query;
+
+const JOTS_DIR: &str = "jots";
+const TAGS_DIR: &str = "tags";
+
+/// Lists every file an export wrote, so the next export knows the directory
+/// is ours and what it's allowed to clean up.
+const MARKER: &str = ".jot-export";
+
+/// The longest a jot's first line can be before it is cut short in a title.
+const TITLE_LEN: usize = 60;
+
+/// What kind of site to write.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum SiteKind {
+    Html,
+    /// A folder of notes that Obsidian, Logseq and the like can open.
+    Markdown,
+}
+
+impl SiteKind {
+    fn extension(self) -> &'static str {
+        match self {
+            SiteKind::Html => "html",
+            SiteKind::Markdown => "md",
+        }
+    }
+}
+
+/// The jots going into a site, grouped up for the index pages.
+struct Site {
+    kind: SiteKind,
+    jots: Vec<Jot>,
+    by_tag: BTreeMap<String, Vec<usize>>,
+    by_date: BTreeMap<NaiveDate, Vec<usize>>,
+}
+
+pub fn export_site_command(
+    config: Config,
+    dir: &Path,
+    query: Query,
+    include_archived: bool,
+    kind: SiteKind,
+) -> Result<()> {
+    // Newest first, like a blog.
+    let jots = stream_all_jots(config, true, include_archived)?
+        .filter(|jot| query.matches(jot))
+        .collect::<Vec<_>>();
+    let site = Site::new(jots, kind);
+
+    prepare_dir(dir)?;
+    let pages = match kind {
+        SiteKind::Html => site.html_pages(),
+        SiteKind::Markdown => site.markdown_pages(),
+    };
+    for (path, contents) in &pages {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents).with_context(|| format!("failed to write {:?}", path))?;
+    }
+    let written = pages
+        .iter()
+        .map(|(path, _contents)| format!("{}\n", path.display()))
+        .collect::<String>();
+    fs::write(dir.join(MARKER), written)?;
+
+    println!(
+        "Exported {} jots and {} tags to {}",
+        site.jots.len(),
+        site.by_tag.len(),
+        dir.join(index_name(kind)).display()
+    );
+    Ok(())
+}
+
+fn index_name(kind: SiteKind) -> String {
+    format!("index.{}", kind.extension())
+}
+
+/// Make sure we can write the site to `dir` without clobbering anything that
+/// isn't ours. Pages from an earlier export are cleared out so jots that have
+/// since been deleted don't hang around, anything else is left alone.
+fn prepare_dir(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        let is_empty = fs::read_dir(dir)
+            .with_context(|| format!("failed to read {:?}", dir))?
+            .next()
+            .is_none();
+        let marker = dir.join(MARKER);
+        if !is_empty && !marker.is_file() {
+            bail!(
+                "{} isn't empty and doesn't look like an earlier export, pick another directory",
+                dir.display()
+            );
+        }
+        if marker.is_file() {
+            let written = fs::read_to_string(&marker)
+                .with_context(|| format!("failed to read {:?}", marker))?;
+            for page in written
+                .lines()
+                .map(Path::new)
+                .filter(|page| is_page_path(page))
+            {
+                let page = dir.join(page);
+                if page.is_file() {
+                    fs::remove_file(&page)
+                        .with_context(|| format!("failed to clear out {:?}", page))?;
+                }
+            }
+            // Only tidy up the folders we made if nobody else put anything in them.
+            for sub_dir in &[JOTS_DIR, TAGS_DIR] {
+                let _ = fs::remove_dir(dir.join(sub_dir));
+            }
+        }
+    }
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {:?}", dir))?;
+    Ok(())
+}
+
+/// Whether a path from the marker is one we could have written, so a mangled
+/// marker can't point us at files outside the export.
+fn is_page_path(path: &Path) -> bool {
+    !path.as_os_str().is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)))
+}
+
+#[test]
+fn test_is_page_path() {
+    assert!(is_page_path(Path::new("index.html")));
+    assert!(is_page_path(Path::new("jots/kw.md")));
+    assert!(!is_page_path(Path::new("")));
+    assert!(!is_page_path(Path::new("../notes.txt")));
+    assert!(!is_page_path(Path::new("/home/me/notes.txt")));
+}
+
+impl Site {
+    fn new(jots: Vec<Jot>, kind: SiteKind) -> Site {
+        let mut by_tag: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        let mut by_date: BTreeMap<NaiveDate, Vec<usize>> = BTreeMap::new();
+        for (i, jot) in jots.iter().enumerate() {
+            for tag in itertools::sorted(&jot.tags) {
+                by_tag.entry(tag.clone()).or_default().push(i);
+            }
+            by_date.entry(jot_date(jot)).or_default().push(i);
+        }
+        Site {
+            kind,
+            jots,
+            by_tag,
+            by_date,
+        }
+    }
+
+    /// Where a jot's page goes, relative to the top of the site.
+    fn jot_path(&self, jot: &Jot) -> PathBuf {
+        // Older notes might not have a uuid but the file name is always unique.
+        let name = match &jot.uuid {
+            Some(uuid) => uuid.clone(),
+            None => jot
+                .path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+        };
+        PathBuf::from(JOTS_DIR).join(format!("{}.{}", name, self.kind.extension()))
+    }
+
+    fn tag_path(&self, tag: &str) -> PathBuf {
+        PathBuf::from(TAGS_DIR).join(format!(
+            "{}.{}",
+            tag.trim_start_matches('@'),
+            self.kind.extension()
+        ))
+    }
+
+    /// Link from a page in a sub directory back up to `path`.
+    fn link_up(path: &Path) -> String {
+        format!("../{}", path.display())
+    }
+
+    fn html_pages(&self) -> Vec<(PathBuf, String)> {
+        let mut pages = vec![];
+
+        let mut body = String::from("<h1>Journal</h1>\n");
+        body += &self.html_list(&(0..self.jots.len()).collect::<Vec<_>>(), "");
+        pages.push((
+            PathBuf::from(index_name(self.kind)),
+            html_page("Journal", "", &body),
+        ));
+
+        let mut body = String::from("<h1>Tags</h1>\n<ul>\n");
+        for (tag, jots) in &self.by_tag {
+            body += &format!(
+                "<li><a href=\"{}\">{}</a> ({})</li>\n",
+                self.tag_path(tag).display(),
+                tag,
+                jots.len()
+            );
+        }
+        body += "</ul>\n";
+        pages.push((PathBuf::from("tags.html"), html_page("Tags", "", &body)));
+
+        let mut body = String::from("<h1>Dates</h1>\n");
+        for (date, jots) in self.by_date.iter().rev() {
+            body += &format!("<h2>{}</h2>\n", date.format(DAY_DISPLAY_FORMAT));
+            body += &self.html_list(jots, "");
+        }
+        pages.push((PathBuf::from("dates.html"), html_page("Dates", "", &body)));
+
+        for (tag, jots) in &self.by_tag {
+            let body = format!("<h1>{}</h1>\n{}", tag, self.html_list(jots, "../"));
+            pages.push((self.tag_path(tag), html_page(tag, "../", &body)));
+        }
+
+        for jot in &self.jots {
+            let title = jot_title(jot);
+            let mut body = format!("<h1>{}</h1>\n<p class=\"meta\">", escape_html(&title));
+            body += &escape_html(&jot_meta(jot));
+            body += "</p>\n";
+            body += &format!(
+                "<div class=\"message\">{}</div>\n",
+                link_tags_html(&escape_html(&jot.message), |tag| {
+                    Self::link_up(&self.tag_path(tag))
+                })
+            );
+            pages.push((self.jot_path(jot), html_page(&title, "../", &body)));
+        }
+
+        pages
+    }
+
+    /// A list of links to jots, `prefix` gets from the current page back to the top.
+    fn html_list(&self, jots: &[usize], prefix: &str) -> String {
+        let mut list = String::from("<ul>\n");
+        for &i in jots {
+            let jot = &self.jots[i];
+            list += &format!(
+                "<li><a href=\"{}{}\">{}</a> <span class=\"meta\">{} &middot; {}</span></li>\n",
+                prefix,
+                self.jot_path(jot).display(),
+                escape_html(&jot_title(jot)),
+                jot.type_name(),
+                jot.datetime.format("%Y-%m-%d %H:%M")
+            );
+        }
+        list + "</ul>\n"
+    }
+
+    fn markdown_pages(&self) -> Vec<(PathBuf, String)> {
+        let mut pages = vec![];
+
+        let mut body = String::from("# Journal\n\n[Tags](tags.md) · [Dates](dates.md)\n\n");
+        body += &self.markdown_list(&(0..self.jots.len()).collect::<Vec<_>>(), "");
+        pages.push((PathBuf::from(index_name(self.kind)), body));
+
+        let mut body = String::from("# Tags\n\n");
+        for (tag, jots) in &self.by_tag {
+            body += &format!(
+                "- [{}]({}) ({})\n",
+                tag,
+                self.tag_path(tag).display(),
+                jots.len()
+            );
+        }
+        pages.push((PathBuf::from("tags.md"), body));
+
+        let mut body = String::from("# Dates\n");
+        for (date, jots) in self.by_date.iter().rev() {
+            body += &format!("\n## {}\n\n", date.format(DAY_DISPLAY_FORMAT));
+            body += &self.markdown_list(jots, "");
+        }
+        pages.push((PathBuf::from("dates.md"), body));
+
+        for (tag, jots) in &self.by_tag {
+            let body = format!("# {}\n\n{}", tag, self.markdown_list(jots, "../"));
+            pages.push((self.tag_path(tag), body));
+        }
+
+        for jot in &self.jots {
+            // Front matter so note apps pick up the tags and dates on their own.
+            let mut page = String::from("---\n");
+            if let Some(uuid) = &jot.uuid {
+                page += &format!("id: {}\n", uuid);
+            }
+            page += &format!("created: {}\n", jot.datetime.to_rfc3339());
+            page += &format!("type: {}\n", jot.type_name());
+            let tags = itertools::sorted(&jot.tags)
+                .map(|tag| tag.trim_start_matches('@'))
+                .collect::<Vec<_>>();
+            page += &format!("tags: [{}]\n", tags.join(", "));
+            page += "---\n\n";
+            page += &format!("# {}\n\n", jot_title(jot));
+            page += &format!("_{}_\n\n", jot_meta(jot));
+            page += &link_tags_markdown(&jot.message, |tag| Self::link_up(&self.tag_path(tag)));
+            page += "\n";
+            pages.push((self.jot_path(jot), page));
+        }
+
+        pages
+    }
+
+    fn markdown_list(&self, jots: &[usize], prefix: &str) -> String {
+        jots.iter()
+            .map(|&i| {
+                let jot = &self.jots[i];
+                format!(
+                    "- [{}]({}{}) {} · {}\n",
+                    jot_title(jot).replace(']', "\\]"),
+                    prefix,
+                    self.jot_path(jot).display(),
+                    jot.type_name(),
+                    jot.datetime.format("%Y-%m-%d %H:%M")
+                )
+            })
+            .collect()
+    }
+}
+
+/// Day entries belong to the day they are about, everything else to the day it was written.
+fn jot_date(jot: &Jot) -> NaiveDate {
+    match jot.msg_type {
+        MessageType::Day(date) => date,
+        _ => jot.datetime.date().naive_local(),
+    }
+}
+
+/// The first line of a jot, cut short if it goes on and on.
+fn jot_title(jot: &Jot) -> String {
+    let first = jot.message.lines().next().unwrap_or("").trim();
+    if first.is_empty() {
+        return jot.type_name().to_string();
+    }
+    if first.chars().count() > TITLE_LEN {
+        format!(
+            "{}…",
+            first.chars().take(TITLE_LEN).collect::<String>().trim_end()
+        )
+    } else {
+        first.to_string()
+    }
+}
+
+/// The line under a jot's title saying what it is and when it was written.
+fn jot_meta(jot: &Jot) -> String {
+    let mut meta = format!(
+        "{} written {}",
+        jot.type_name(),
+        jot.datetime.format("%Y-%m-%d %H:%M")
+    );
+    if let MessageType::Todo(todo) = &jot.msg_type {
+        meta += &format!(", {}", todo.state.name());
+        if let Some(since) = todo.state.since() {
+            meta += &format!(" since {}", since.format("%Y-%m-%d %H:%M"));
+        }
+    }
+    if jot.archived {
+        meta += &format!(", {}", ARCHIVED);
+    }
+    meta
+}
+
+fn html_page(title: &str, prefix: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 50em; margin: 2em auto; padding: 0 1em; line-height: 1.5; }}
+.meta {{ color: #777; }}
+.message {{ white-space: pre-wrap; }}
+</style>
+</head>
+<body>
+<nav><a href="{prefix}index.html">Journal</a> &middot; <a href="{prefix}tags.html">Tags</a> &middot; <a href="{prefix}dates.html">Dates</a></nav>
+{body}</body>
+</html>
+"#,
+        title = escape_html(title),
+        prefix = prefix,
+        body = body
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Turn every `@tag` in already escaped html into a link to the tag's page.
+fn link_tags_html(html: &str, href: impl Fn(&str) -> String) -> String {
+    TAG_RE
+        .replace_all(html, |caps: &regex::Captures| {
+            format!("<a href=\"{}\">{}</a>", href(&caps[0]), &caps[0])
+        })
+        .to_string()
+}
+
+fn link_tags_markdown(markdown: &str, href: impl Fn(&str) -> String) -> String {
+    TAG_RE
+        .replace_all(markdown, |caps: &regex::Captures| {
+            format!("[{}]({})", &caps[0], href(&caps[0]))
+        })
+        .to_string()
+}
+
+#[test]
+fn test_link_tags() {
+    let href = |tag: &str| format!("../tags/{}.x", tag.trim_start_matches('@'));
+    assert_eq!(
+        link_tags_html(&escape_html("<b> @work & @home_2"), href),
+        "&lt;b&gt; <a href=\"../tags/work.x\">@work</a> &amp; <a href=\"../tags/home_2.x\">@home_2</a>"
+    );
+    assert_eq!(
+        link_tags_markdown("ship it @work", href),
+        "ship it [@work](../tags/work.x)"
+    );
+}
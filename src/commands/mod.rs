@@ -1,4 +1,8 @@
+pub mod archive;
 pub mod create;
+pub mod day;
 pub mod edit;
+pub mod export;
 pub mod tags;
+pub mod timesheet;
 pub mod view;
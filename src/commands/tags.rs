@@ -1,11 +1,16 @@
 /// Commands that work with tags live here.
 use crate::config::Config;
-use crate::jot::{stream_jots, MessageType};
-use anyhow::Result;
+use crate::constants::*;
+use crate::format::{write_tags, Format, TagRecord};
+use crate::jot::{find_tags, stream_all_jots, stream_jots, Jot, MessageType, TAG_RE};
+use crate::output;
+use crate::utils::{confirm, edit_distance};
+use anyhow::{anyhow, Result};
 use colorful::Colorful;
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-pub fn tags_command(config: Config) -> Result<()> {
+pub fn tags_command(config: Config, format: Format) -> Result<()> {
     let mut all_tags = HashSet::new();
     let mut notes = HashMap::new();
     let mut todos = HashMap::new();
@@ -19,7 +24,8 @@ pub fn tags_command(config: Config) -> Result<()> {
     };
     for jot in stream_jots(config, false)? {
         match jot.msg_type {
-            MessageType::Note => {
+            // Daily entries and custom types are just fancy notes.
+            MessageType::Note | MessageType::Day(_) | MessageType::Custom(_) => {
                 for tag in jot.tags {
                     increment(&mut notes, &tag);
                     all_tags.insert(tag);
@@ -34,6 +40,17 @@ pub fn tags_command(config: Config) -> Result<()> {
         }
     }
 
+    if format != Format::Pretty {
+        let records = itertools::sorted(all_tags)
+            .map(|tag| TagRecord {
+                notes: notes.get(&tag).copied().unwrap_or(0),
+                todos: todos.get(&tag).copied().unwrap_or(0),
+                tag,
+            })
+            .collect::<Vec<_>>();
+        return write_tags(&records, format);
+    }
+
     use prettytable::{format, Cell, Table};
     let mut table = Table::new();
     let format = format::FormatBuilder::new()
@@ -47,7 +64,7 @@ pub fn tags_command(config: Config) -> Result<()> {
         "notes".bold().blue(),
         "todos".bold().magenta(),
     ]);
-    for tag in itertools::sorted(all_tags.into_iter()) {
+    for tag in itertools::sorted(all_tags) {
         let notes_cell = Cell::new_align(
             &notes
                 .get(&tag)
@@ -68,6 +85,251 @@ pub fn tags_command(config: Config) -> Result<()> {
 
         table.add_row(row![tag, notes_cell, todos_cell]);
     }
-    table.printstd();
+    print!("{}", output::paint(&table.to_string()));
+    Ok(())
+}
+
+/// Every tag used in the journal, leaving out the jot at `skip` (the one being edited).
+pub fn all_tags(config: Config, skip: Option<&Path>) -> Result<HashSet<String>> {
+    Ok(stream_jots(config, false)?
+        .filter(|jot| Some(jot.path.as_path()) != skip)
+        .flat_map(|jot| jot.tags)
+        .collect())
+}
+
+/// Swap every use of one tag in a message for another, leaving longer tags
+/// that just start the same way alone.
+pub fn replace_tag(message: &str, from: &str, to: &str) -> String {
+    TAG_RE
+        .replace_all(message, |caps: &regex::Captures| {
+            if &caps[0] == from {
+                to.to_string()
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .to_string()
+}
+
+#[test]
+fn test_replace_tag() {
+    assert_eq!(
+        replace_tag("@meetng with @meetngs about @meetng", "@meetng", "@meeting"),
+        "@meeting with @meetngs about @meeting"
+    );
+}
+
+/// Take a tag out of a message along with the space around it.
+pub fn remove_tag(message: &str, tag: &str) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    for found in TAG_RE
+        .find_iter(message)
+        .filter(|found| found.as_str() == tag)
+    {
+        let before = &message[last..found.start()];
+        let trimmed = before.trim_end_matches([' ', '\t']);
+        let at_line_start = trimmed.is_empty() && (last == 0 || message[..last].ends_with('\n'))
+            || trimmed.ends_with('\n');
+        // Keep the indentation of a tag starting a line and eat the space after it instead.
+        if at_line_start {
+            result.push_str(before);
+            last = found.end()
+                + message[found.end()..]
+                    .chars()
+                    .take_while(|c| *c == ' ' || *c == '\t')
+                    .count();
+        } else {
+            result.push_str(trimmed);
+            last = found.end();
+        }
+    }
+    result.push_str(&message[last..]);
+    result
+}
+
+#[test]
+fn test_remove_tag() {
+    assert_eq!(remove_tag("standup @work @ops", "@work"), "standup @ops");
+    assert_eq!(
+        remove_tag("@work standup\n  @work notes", "@work"),
+        "standup\n  notes"
+    );
+    assert_eq!(remove_tag("keep @workshop", "@work"), "keep @workshop");
+}
+
+/// New tags in the message that look like a typo of one we already use,
+/// along with the tag that was probably meant. Tags in `kept` were already on
+/// the jot before it was edited so they are left alone.
+pub fn likely_typos(
+    existing: &HashSet<String>,
+    kept: &HashSet<String>,
+    message: &str,
+) -> Vec<(String, String)> {
+    itertools::sorted(find_tags(message))
+        .filter(|tag| !existing.contains(tag) && !kept.contains(tag))
+        .filter_map(|tag| {
+            // Short tags only get one mistake, otherwise everything looks like a typo.
+            let max_distance = if tag.chars().count() <= 5 { 1 } else { 2 };
+            itertools::sorted(existing)
+                .map(|known| (edit_distance(&tag, known), known))
+                .filter(|(distance, _)| *distance <= max_distance)
+                .min_by_key(|(distance, _)| *distance)
+                .map(|(_, known)| (tag.clone(), known.clone()))
+        })
+        .collect()
+}
+
+#[test]
+fn test_likely_typos() {
+    let existing = ["@meeting", "@ops", "@home"]
+        .iter()
+        .map(|tag| tag.to_string())
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        likely_typos(
+            &existing,
+            &HashSet::new(),
+            "@meetng about @ops and @homework @xyz"
+        ),
+        vec![("@meetng".to_string(), "@meeting".to_string())]
+    );
+    let kept = ["@meetng".to_string()].iter().cloned().collect();
+    assert!(likely_typos(&existing, &kept, "@meetng about @ops").is_empty());
+}
+
+/// Look for tags in a message that are probably typos of existing ones. When
+/// we can ask we offer to fix them, otherwise we just warn about them. Only
+/// tags that aren't in `kept` (the ones the jot had before) are looked at.
+pub fn check_tag_typos(
+    config: Config,
+    message: &str,
+    skip: Option<&Path>,
+    kept: &HashSet<String>,
+    interactive: bool,
+) -> Result<String> {
+    let existing = all_tags(config, skip)?;
+    let mut message = message.to_string();
+    for (typo, known) in likely_typos(&existing, kept, &message) {
+        if interactive {
+            if confirm(&output::paint(&format!(
+                "{} looks like {}, fix it?",
+                typo,
+                known.clone().bold()
+            )))? {
+                message = replace_tag(&message, &typo, &known);
+            }
+        } else {
+            eprintln!("warning: {} looks like a typo of {}", typo, known);
+        }
+    }
+    Ok(message)
+}
+
+/// A change to make to tags across the whole journal.
+pub enum TagChange {
+    /// Replace each of these tags with another one (rename and merge).
+    Replace(Vec<String>, String),
+    /// Take a tag out of every jot.
+    Remove(String),
+}
+
+impl TagChange {
+    fn apply(&self, message: &str) -> String {
+        match self {
+            TagChange::Replace(from, to) => {
+                let mut message = message.to_string();
+                let mut replaced = find_tags(&message).contains(to);
+                // Merging into one of the tags being merged leaves that one alone.
+                for tag in from.iter().filter(|tag| *tag != to) {
+                    // Merging two tags that were on the same jot would leave it tagged twice.
+                    if replaced && find_tags(&message).contains(tag) {
+                        message = remove_tag(&message, tag);
+                    } else if find_tags(&message).contains(tag) {
+                        message = replace_tag(&message, tag, to);
+                        replaced = true;
+                    }
+                }
+                message
+            }
+            TagChange::Remove(tag) => remove_tag(message, tag),
+        }
+    }
+}
+
+/// Rewrite the tags in every jot. All of the new files are written to a
+/// scratch directory the journal reader skips before any of the old ones are
+/// replaced, and if replacing one fails the ones already replaced are put back.
+pub fn change_tags_command(config: Config, change: TagChange, dry_run: bool) -> Result<()> {
+    let mut changed = vec![];
+    for mut jot in stream_all_jots(config.clone(), false, true)? {
+        let message = change.apply(&jot.message);
+        if message != jot.message {
+            let original = jot.to_string();
+            jot.message = message;
+            changed.push((original, jot));
+        }
+    }
+
+    if dry_run {
+        for (_original, jot) in &changed {
+            jot.pprint();
+            println!();
+        }
+        println!("{} jots would be changed", changed.len());
+        return Ok(());
+    }
+
+    let tmp_dir = config.journal_path.join(TMP_DIR);
+    std::fs::create_dir_all(&tmp_dir)?;
+    let result = replace_jots(&tmp_dir, &changed);
+    let _ = std::fs::remove_dir_all(&tmp_dir);
+    result?;
+    println!("changed {} jots", changed.len());
     Ok(())
 }
+
+/// Swap in the new version of each jot, file names are unique across the
+/// journal and the archive so they can share the scratch directory.
+fn replace_jots(tmp_dir: &Path, changed: &[(String, Jot)]) -> Result<()> {
+    let mut written: Vec<PathBuf> = vec![];
+    for (_original, jot) in changed {
+        let tmp_path = tmp_dir.join(jot.path.file_name().unwrap_or_default());
+        std::fs::write(&tmp_path, jot.to_string())?;
+        written.push(tmp_path);
+    }
+
+    for (i, (tmp_path, (_original, jot))) in written.iter().zip(changed).enumerate() {
+        if let Err(err) = std::fs::rename(tmp_path, &jot.path) {
+            for (original, jot) in &changed[..i] {
+                let _ = std::fs::write(&jot.path, original);
+            }
+            return Err(anyhow!(
+                "couldn't replace {}, nothing was changed: {}",
+                jot.path.to_string_lossy(),
+                err
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_tag_change() {
+    let merge = TagChange::Replace(
+        vec!["@proj".to_string(), "@projx".to_string()],
+        "@atlas".to_string(),
+    );
+    assert_eq!(merge.apply("kickoff @proj"), "kickoff @atlas");
+    assert_eq!(merge.apply("kickoff @proj @projx"), "kickoff @atlas");
+    assert_eq!(merge.apply("@atlas kickoff @projx"), "@atlas kickoff");
+    assert_eq!(merge.apply("nothing here"), "nothing here");
+
+    let into_source = TagChange::Replace(
+        vec!["@mtg".to_string(), "@meeting".to_string()],
+        "@meeting".to_string(),
+    );
+    assert_eq!(into_source.apply("standup @meeting"), "standup @meeting");
+    assert_eq!(into_source.apply("sync @mtg"), "sync @meeting");
+    assert_eq!(into_source.apply("sync @mtg @meeting"), "sync @meeting");
+}
@@ -0,0 +1,77 @@
+/// Commands for reporting on time tracked against todos live here.
+use crate::config::Config;
+use crate::jot::{stream_all_jots, MessageType};
+use crate::output;
+use crate::utils::{parse_human_range, pretty_work_duration};
+use anyhow::Result;
+use chrono::prelude::*;
+use chrono::Duration;
+use colorful::Colorful;
+use std::collections::HashMap;
+
+const UNTAGGED: &str = "untagged";
+
+pub fn timesheet_command(config: Config, range: &str) -> Result<()> {
+    let (start, end) = parse_human_range(range)?;
+    let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
+
+    // For each tag keep track of the todos worked on and for how long.
+    let mut by_tag: HashMap<String, Vec<(String, Duration)>> = HashMap::new();
+    let mut total = Duration::zero();
+    // Time worked on archived todos still counts.
+    for jot in stream_all_jots(config, false, true)? {
+        let todo = match &jot.msg_type {
+            MessageType::Todo(todo) => todo,
+            _ => continue,
+        };
+
+        let worked = todo
+            .worked
+            .iter()
+            .map(|interval| interval.overlap(start, end, now))
+            .fold(Duration::zero(), |total, worked| total + worked);
+        if worked <= Duration::zero() {
+            continue;
+        }
+        total = total + worked;
+
+        let description = format!(
+            "[{}] {}",
+            jot.uuid.clone().unwrap_or_else(|| jot.id.to_string()),
+            jot.message.lines().next().unwrap_or("")
+        );
+        let mut tags = jot.tags.iter().cloned().collect::<Vec<_>>();
+        if tags.is_empty() {
+            tags.push(UNTAGGED.to_string());
+        }
+        for tag in tags {
+            by_tag
+                .entry(tag)
+                .or_default()
+                .push((description.clone(), worked));
+        }
+    }
+
+    use prettytable::{format, Cell, Table};
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.add_row(row!["tag".bold(), "time".bold()]);
+    for (tag, todos) in itertools::sorted(by_tag) {
+        let tag_total = todos
+            .iter()
+            .fold(Duration::zero(), |total, (_, worked)| total + *worked);
+        table.add_row(row![
+            tag.bold(),
+            pretty_work_duration(tag_total).blue().bold()
+        ]);
+        for (description, worked) in todos {
+            table.add_row(row![
+                format!("  {}", description),
+                Cell::new(&pretty_work_duration(worked))
+            ]);
+        }
+    }
+    table.add_row(row!["total".bold(), pretty_work_duration(total).bold()]);
+    print!("{}", output::paint(&table.to_string()));
+    Ok(())
+}
@@ -1,8 +1,11 @@
 /// Commands related to viewing notes/todos/reminders live here.
 use crate::config::Config;
 use crate::constants::*;
-use crate::jot::{stream_jots, Jot, MessageType};
-use anyhow::Result;
+use crate::format::{write_jots, Format};
+use crate::jot::{stream_all_jots, stream_jots, Jot, MessageType};
+use crate::output;
+use anyhow::{anyhow, Context, Result};
+use chrono::prelude::*;
 use colorful::Colorful;
 use regex::Regex;
 use std::collections::HashSet;
@@ -86,39 +89,172 @@ pub fn interactive_search(config: Config) -> Result<()> {
         term.clear_screen()?;
 
         for (highlighted_msg, jot, _matched_chars) in matched_jots.iter().take(5) {
-            jot.pprint_with_custom_msg(Some(&highlighted_msg));
+            jot.pprint_with_custom_msg(Some(highlighted_msg));
         }
 
         println!("search: {}", search_string);
     }
 }
 
+/// The tag (`-t`), grep (`-g`) and date (`-d`) filters shared by the view
+/// commands and the commands that change jots in bulk.
+#[derive(Debug)]
+pub struct Query {
+    tags: HashSet<String>,
+    range: Option<(NaiveDateTime, NaiveDateTime)>,
+    greps: Vec<Regex>,
+}
+
+impl Query {
+    pub fn from_matches(matches: &clap::ArgMatches) -> Result<Query> {
+        let tags = matches
+            .values_of("TAG")
+            .map(|m| m.map(|tag| tag.to_string()).collect())
+            .unwrap_or_default();
+        let range = matches
+            .value_of("RNG")
+            .map(|st| {
+                two_timer::parse(st, None)
+                    .map(|(start, end, _)| (start, end))
+                    .map_err(|_| anyhow!("failed to parse human time {:?}", st))
+            })
+            .transpose()?;
+        let greps = matches
+            .values_of("GREP")
+            .map(|m| {
+                m.map(|grep| Regex::new(grep).with_context(|| format!("invalid regex {:?}", grep)))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Query { tags, range, greps })
+    }
+
+    /// Whether any filters were given at all.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.range.is_none() && self.greps.is_empty()
+    }
+
+    pub fn matches(&self, jot: &Jot) -> bool {
+        if let Some((start, end)) = self.range {
+            let local = jot.datetime.naive_local();
+            if local > end || local < start {
+                return false;
+            }
+        }
+
+        self.tags.iter().all(|tag| jot.tags.contains(tag))
+            && self.greps.iter().all(|re| re.is_match(&jot.message))
+    }
+}
+
+/// Color everything the greps matched, overlapping matches are merged so
+/// the color codes don't land in the middle of each other.
+fn highlight_matches(message: &str, greps: &[Regex]) -> String {
+    let mut ranges = greps
+        .iter()
+        .flat_map(|re| re.find_iter(message).map(|m| (m.start(), m.end())))
+        .collect::<Vec<_>>();
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = vec![];
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = std::cmp::max(last.1, end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    // Going in backwards order to preserve the indices.
+    let mut msg = message.to_string();
+    for (start, end) in merged.into_iter().rev() {
+        let highlighted = msg[start..end].to_string().red();
+        msg.replace_range(start..end, &highlighted.to_string());
+    }
+    msg
+}
+
+#[test]
+fn test_highlight_matches() {
+    let greps = vec![
+        Regex::new("deploy the").unwrap(),
+        Regex::new("the api").unwrap(),
+    ];
+    assert_eq!(highlight_matches("ship it", &greps), "ship it");
+    let highlighted = highlight_matches("deploy the api", &greps);
+    assert_eq!(highlighted, format!("{}", "deploy the api".red()));
+}
+
 pub fn display(config: Config, read_cmd: &str, matches: clap::ArgMatches) -> Result<()> {
     let reverse = matches
         .subcommand_matches(read_cmd)
         .unwrap()
         .is_present("REVERSE");
 
-    let range = matches
+    let query = Query::from_matches(matches.subcommand_matches(read_cmd).unwrap())?;
+
+    let format = Format::from_string(
+        matches
+            .subcommand_matches(read_cmd)
+            .unwrap()
+            .value_of("FORMAT")
+            .unwrap_or("pretty"),
+    )?;
+
+    let pager = output::pager_command(
+        config.pager.as_deref(),
+        matches
+            .subcommand_matches(read_cmd)
+            .unwrap()
+            .is_present("NO_PAGER"),
+    );
+
+    let oneline = config.oneline
+        || matches
+            .subcommand_matches(read_cmd)
+            .unwrap()
+            .is_present("ONELINE");
+
+    let sort = matches
         .subcommand_matches(read_cmd)
         .unwrap()
-        .value_of("RNG");
+        .value_of("SORT");
 
-    let human_range =
-        range.map(|st| two_timer::parse(st, None).expect("failed to parse human time"));
+    let only_overdue = matches
+        .subcommand_matches(read_cmd)
+        .unwrap()
+        .is_present("OVERDUE");
 
-    let jots: Box<dyn Iterator<Item = Jot>> = Box::new(stream_jots(config, reverse)?);
-    for jot in jots {
-        if let Some((start, end, some_bool)) = human_range {
-            let local = jot.datetime.naive_local();
+    let due_range = matches
+        .subcommand_matches(read_cmd)
+        .unwrap()
+        .value_of("DUE")
+        .map(crate::utils::parse_human_due)
+        .transpose()?;
 
-            if local > end || local < start {
-                continue;
-            }
-        }
+    let states = matches
+        .subcommand_matches(read_cmd)
+        .unwrap()
+        .values_of("STATE")
+        .map(|m| m.collect::<HashSet<&str>>());
+
+    let now: DateTime<Local> = Local::now();
+
+    let mut to_display = vec![];
+    let include_archived = matches
+        .subcommand_matches(read_cmd)
+        .unwrap()
+        .is_present("INCLUDE_ARCHIVED");
 
+    let jots: Box<dyn Iterator<Item = Jot>> =
+        Box::new(stream_all_jots(config, reverse, include_archived)?);
+    for jot in jots {
         // See if we need to filter by the message type
-        if read_cmd != "cat" {
+        if read_cmd == PINNED {
+            if !jot.pinned {
+                continue;
+            }
+        } else if read_cmd != "cat" {
             match jot.msg_type {
                 MessageType::Note => {
                     if read_cmd != NOTES {
@@ -131,69 +267,118 @@ pub fn display(config: Config, read_cmd: &str, matches: clap::ArgMatches) -> Res
                         continue;
                     }
                 }
-            }
-        }
-
-        let formatted_msg = crate::utils::break_apart_long_string(&jot.message.clone());
-        let mut msg = formatted_msg.clone();
 
-        if let Some(sub_matches) = matches.subcommand_matches(read_cmd) {
-            // Skip checks.
-            let tags = sub_matches
-                .values_of("TAG")
-                .map(|m| m.collect::<HashSet<&str>>())
-                .unwrap_or_default();
+                MessageType::Day(_) => {
+                    if read_cmd != DAYS {
+                        continue;
+                    }
+                }
 
-            if !(tags.is_empty() || tags.iter().all(|tag| jot.tags.contains(*tag))) {
-                continue;
+                MessageType::Custom(ref jot_type) => {
+                    if read_cmd != jot_type.plural {
+                        continue;
+                    }
+                }
             }
+        }
 
-            let greps = sub_matches
-                .values_of("GREP")
-                .map(|m| {
-                    m.map(|grep| {
-                        let re_attempt = Regex::new(grep);
-                        match re_attempt {
-                            Ok(re) => re,
-                            Err(err) => {
-                                println!("invalid regex {:?} error={:?}", grep, err);
-                                std::process::exit(1);
-                            }
+        if only_overdue || due_range.is_some() || states.is_some() {
+            match &jot.msg_type {
+                MessageType::Todo(todo) => {
+                    if let Some(states) = &states {
+                        if !states.contains(todo.state.name()) {
+                            continue;
                         }
-                    })
-                    .collect::<Vec<_>>()
-                })
-                .unwrap_or_default();
+                    }
 
-            if !(greps.is_empty() || greps.iter().all(|re| re.find(&formatted_msg).is_some())) {
-                continue;
-            } else {
-                // We did find some grep'd things. Update and highlight our message.
-
-                // We need to go in backwards order to preserve the indices.
-                for re in greps {
-                    let found = re
-                        .find_iter(&formatted_msg)
-                        .collect::<Vec<_>>()
-                        .into_iter()
-                        .rev();
-                    for m in found {
-                        let highlighted = &msg[m.start()..m.end()].to_string().red();
-                        msg.replace_range(m.start()..m.end(), &highlighted.to_string());
+                    if only_overdue && !todo.is_overdue(now) {
+                        continue;
+                    }
+
+                    if let Some((start, end)) = due_range {
+                        match todo.due {
+                            Some(due) if due >= start && due <= end => {}
+                            _ => continue,
+                        }
                     }
                 }
+                _ => continue,
             }
         }
 
-        jot.pprint_with_custom_msg(Some(&msg));
-        println!();
+        if !query.matches(&jot) {
+            continue;
+        }
+
+        // Highlight whatever the greps found in the message they matched, and
+        // only wrap it after so matches across a line break are shown too.
+        let msg =
+            crate::utils::break_apart_long_string(&highlight_matches(&jot.message, &query.greps));
+
+        to_display.push((jot, msg));
+    }
+
+    match sort {
+        Some("due") => {
+            // Todos without a due date go last.
+            to_display.sort_by_key(|(jot, _)| match &jot.msg_type {
+                MessageType::Todo(todo) => (todo.due.is_none(), todo.due),
+                _ => (true, None),
+            })
+        }
+        Some("priority") => to_display.sort_by_key(|(jot, _)| match &jot.msg_type {
+            MessageType::Todo(todo) => (
+                std::cmp::Reverse(todo.priority),
+                todo.due.is_none(),
+                todo.due,
+            ),
+            _ => (std::cmp::Reverse(None), true, None),
+        }),
+        _ => {}
+    }
+
+    if read_cmd == DAYS {
+        // Days go in calendar order rather than the order they were written.
+        to_display.sort_by_key(|(jot, _)| match jot.msg_type {
+            MessageType::Day(date) => date,
+            _ => jot.datetime.date().naive_local(),
+        });
+        if reverse {
+            to_display.reverse();
+        }
     }
-    Ok(())
+
+    // Pinned jots stay at hand at the top of everything else.
+    if read_cmd == "cat" || read_cmd == NOTES {
+        to_display.sort_by_key(|(jot, _)| !jot.pinned);
+    }
+
+    if format != Format::Pretty {
+        let jots = to_display
+            .into_iter()
+            .map(|(jot, _)| jot)
+            .collect::<Vec<_>>();
+        return write_jots(&jots, format);
+    }
+
+    if oneline {
+        let rows = to_display
+            .iter()
+            .map(|(jot, _)| jot.oneline_columns())
+            .collect::<Vec<_>>();
+        return output::page(&output::columns(&rows), pager.as_deref());
+    }
+
+    let rendered = to_display
+        .into_iter()
+        .map(|(jot, msg)| format!("{}\n", jot.render_with_custom_msg(Some(&msg))))
+        .collect::<String>();
+    output::page(&rendered, pager.as_deref())
 }
 
+/// Every uuid in use, archived jots included so we never hand one out twice.
 pub fn get_all_uuids(config: Config) -> Result<HashSet<String>> {
-    Ok(stream_jots(config, false)?
-        .map(|jot| jot.uuid)
-        .filter_map(|uuid| uuid)
+    Ok(stream_all_jots(config, false, true)?
+        .filter_map(|jot| jot.uuid)
         .collect())
 }
@@ -1,4 +1,9 @@
-use anyhow::{Context, Result};
+use crate::autotag::{normalize_tag, AutoTagger, TagRule};
+use crate::constants::*;
+use crate::output::MIN_WRAP_WIDTH;
+use crate::utils::parse_age;
+use anyhow::{bail, Context, Result};
+use clap::App;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::prelude::*;
@@ -7,6 +12,164 @@ use std::path::PathBuf;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
     pub journal_path: PathBuf,
+
+    /// Complete a todo once every item in its checklist has been checked off.
+    #[serde(default)]
+    pub complete_finished_checklists: bool,
+
+    /// Extra kinds of jots (ideas, meetings, decisions...) on top of notes and todos.
+    // Written out empty it would clash with `[[types]]` added below it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<JotType>,
+
+    /// Rules for tagging jots automatically when they are written.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag_rules: Vec<TagRule>,
+
+    /// Rules for which jots `jot archive --auto` should put away.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retention: Vec<RetentionRule>,
+
+    /// How wide to wrap jots when the output isn't a terminal, defaults to 80.
+    #[serde(default)]
+    pub wrap_width: Option<usize>,
+
+    /// The pager for long listings, overrides `$PAGER`. Empty turns paging off.
+    #[serde(default)]
+    pub pager: Option<String>,
+
+    /// List one jot per line in the view commands, like `--oneline`.
+    #[serde(default)]
+    pub oneline: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JotType {
+    /// The command used to create one, e.g. `idea`.
+    pub name: String,
+    /// The command used to view them all, e.g. `ideas`.
+    pub plural: String,
+    /// The keyword written to the jot header, defaults to the name.
+    pub header: Option<String>,
+    /// The color used when printing, defaults to blue.
+    pub color: Option<String>,
+}
+
+/// Archive jots once they get old, e.g. todos completed more than 90 days ago.
+/// Every part of the rule that is given has to match.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RetentionRule {
+    /// How old a jot has to be, e.g. "90 days". Finished todos are aged from
+    /// when they were finished, everything else from when it was written.
+    pub older_than: String,
+    /// Only this type of jot (note, todo, day or a custom type).
+    #[serde(rename = "type")]
+    pub jot_type: Option<String>,
+    /// Only todos in this state, e.g. "done".
+    pub state: Option<String>,
+    /// Only jots with all of these tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl JotType {
+    /// A type we found in the journal but isn't in the config (anymore).
+    pub fn unknown(header: &str) -> JotType {
+        JotType {
+            name: header.to_string(),
+            plural: format!("{}s", header),
+            header: Some(header.to_string()),
+            color: None,
+        }
+    }
+
+    pub fn header(&self) -> &str {
+        self.header.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Make sure custom types don't step on the built in ones.
+fn validate_types(types: &[JotType]) -> Result<()> {
+    let reserved = [
+        NOTE,
+        NOTES,
+        TODO,
+        TODOS,
+        DAY,
+        DAYS,
+        PINNED,
+        TODO_HEADER,
+        DAY_HEADER,
+    ];
+    for jot_type in types {
+        for word in &[
+            jot_type.name.as_str(),
+            jot_type.plural.as_str(),
+            jot_type.header(),
+        ] {
+            if reserved.contains(word) {
+                bail!(
+                    "the custom type {:?} can't use the built in name {:?}",
+                    jot_type.name,
+                    word
+                );
+            }
+            if word.is_empty() || word.contains(|c: char| c.is_whitespace() || c == '=' || c == ']')
+            {
+                bail!(
+                    "the custom type {:?} has an invalid name {:?}",
+                    jot_type.name,
+                    word
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Custom types get commands of their own, so they can't share a name with a
+/// built in command, it would hide it.
+pub fn validate_type_commands(types: &[JotType], commands: &[App]) -> Result<()> {
+    for jot_type in types {
+        for word in &[jot_type.name.as_str(), jot_type.plural.as_str()] {
+            // clap adds the help command itself.
+            if *word == "help" || commands.iter().any(|command| command.get_name() == *word) {
+                bail!(
+                    "the custom type {:?} can't use the name of the built in command {:?}",
+                    jot_type.name,
+                    word
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Make sure the retention rules make sense before we archive anything with them.
+fn validate_retention(config: &Config) -> Result<()> {
+    for rule in &config.retention {
+        parse_age(&rule.older_than)?;
+        if let Some(state) = &rule.state {
+            if !TODO_STATES.contains(&state.as_str()) {
+                bail!(
+                    "the retention rule state {:?} should be one of {}",
+                    state,
+                    TODO_STATES.join(", ")
+                );
+            }
+        }
+        for tag in &rule.tags {
+            normalize_tag(tag)?;
+        }
+        if let Some(jot_type) = &rule.jot_type {
+            let known = [NOTE, TODO, DAY].contains(&jot_type.as_str())
+                || config.types.iter().any(|custom| &custom.name == jot_type);
+            if !known {
+                bail!("the retention rule type {:?} isn't a type of jot", jot_type);
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Default journal path for new users.
@@ -26,6 +189,20 @@ fn config_path() -> Option<PathBuf> {
     Some(base)
 }
 
+/// Archived jots are kept in a folder inside the journal.
+pub fn archive_path(config: &Config) -> PathBuf {
+    config.journal_path.join(ARCHIVE)
+}
+
+/// Templates for new jots live next to the config.
+pub fn templates_dir() -> Option<PathBuf> {
+    let mut base = config_path()?;
+    base.pop();
+    base.push("templates");
+
+    Some(base)
+}
+
 /// Loads the config, if it doesn't exist we will create it and return the default.
 pub fn load_config() -> Result<Config> {
     let default_journal_path =
@@ -33,6 +210,13 @@ pub fn load_config() -> Result<Config> {
 
     let default_config = Config {
         journal_path: default_journal_path.clone(),
+        complete_finished_checklists: false,
+        types: vec![],
+        tag_rules: vec![],
+        retention: vec![],
+        wrap_width: None,
+        pager: None,
+        oneline: false,
     };
 
     let path = config_path().context("failed to get config path")?;
@@ -59,11 +243,12 @@ pub fn load_config() -> Result<Config> {
         }
         Ok(default_config)
     } else {
-        let mut file = File::open(path)?;
+        let mut file = File::open(&path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
 
-        let config: Config = toml::from_str(&contents).expect("failed to parse config");
+        let config: Config = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse the config at {}", path.display()))?;
         // Make sure the journal exists.
         if !config.journal_path.exists() {
             println!(
@@ -73,6 +258,19 @@ pub fn load_config() -> Result<Config> {
             std::process::exit(1)
         }
 
+        validate_types(&config.types)?;
+        AutoTagger::new(&config.tag_rules)?;
+        validate_retention(&config)?;
+        if let Some(width) = config.wrap_width {
+            if width < MIN_WRAP_WIDTH {
+                bail!(
+                    "wrap_width should be at least {}, not {}",
+                    MIN_WRAP_WIDTH,
+                    width
+                );
+            }
+        }
+
         Ok(config)
     }
 }
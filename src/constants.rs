@@ -1,8 +1,46 @@
 pub const TODO_NOT_DONE_PLACEHOLDER: &str = "not-done";
 pub const TODO_HEADER: &str = TODO;
+pub const TODO_DUE_KEY: &str = "due";
+pub const TODO_PRIORITY_KEY: &str = "priority";
+pub const TODO_RECURRENCE_KEY: &str = "recur";
+pub const TODO_STATE_KEY: &str = "state";
+pub const TODO_SINCE_KEY: &str = "since";
+pub const TODO_REASON_KEY: &str = "reason";
+pub const TODO_WORKED_KEY: &str = "worked";
+pub const TODO_HISTORY_KEY: &str = "history";
+
+pub const TODO_STATE_OPEN: &str = "open";
+pub const TODO_STATE_IN_PROGRESS: &str = "in-progress";
+pub const TODO_STATE_BLOCKED: &str = "blocked";
+pub const TODO_STATE_WAITING: &str = "waiting";
+pub const TODO_STATE_CANCELLED: &str = "cancelled";
+pub const TODO_STATE_DONE: &str = "done";
+pub const TODO_STATES: [&str; 6] = [
+    TODO_STATE_OPEN,
+    TODO_STATE_IN_PROGRESS,
+    TODO_STATE_BLOCKED,
+    TODO_STATE_WAITING,
+    TODO_STATE_CANCELLED,
+    TODO_STATE_DONE,
+];
 
 pub const TODO: &str = "todo";
 pub const TODOS: &str = "todos";
 
 pub const NOTE: &str = "note";
 pub const NOTES: &str = "notes";
+
+pub const DAY: &str = "day";
+pub const DAYS: &str = "days";
+pub const DAY_HEADER: &str = DAY;
+pub const DAY_HEADER_FORMAT: &str = "%Y-%m-%d";
+pub const DAY_DISPLAY_FORMAT: &str = "%A %B %-d, %Y";
+
+pub const PINNED: &str = "pinned";
+pub const PINNED_KEY: &str = PINNED;
+
+pub const ARCHIVE: &str = "archive";
+pub const JOT_EXTENSION: &str = "jot";
+// Scratch space in the journal for rewriting jots, never read as jots.
+pub const TMP_DIR: &str = ".tmp";
+pub const ARCHIVED: &str = "archived";
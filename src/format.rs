@@ -0,0 +1,277 @@
+/// Machine readable output for the view commands (json, ndjson, csv and tsv).
+use crate::jot::{Jot, MessageType, TodoState};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::io::Write;
+
+pub const FORMATS: [&str; 5] = ["pretty", "json", "ndjson", "csv", "tsv"];
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// The colored boxes meant for people.
+    Pretty,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    pub fn from_string(i: &str) -> Result<Format> {
+        match i {
+            "pretty" => Ok(Format::Pretty),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            _ => bail!("unknown format {:?}, use one of {}", i, FORMATS.join(", ")),
+        }
+    }
+}
+
+/// Everything we know about a jot, flattened out for other programs.
+#[derive(Debug, Serialize)]
+pub struct JotRecord {
+    // Archived jots don't have a numeric id.
+    pub id: Option<usize>,
+    pub uuid: Option<String>,
+    pub created: String,
+    #[serde(rename = "type")]
+    pub jot_type: String,
+    pub day: Option<String>,
+    pub state: Option<String>,
+    pub state_since: Option<String>,
+    pub completed: Option<String>,
+    pub reason: Option<String>,
+    pub due: Option<String>,
+    pub priority: Option<String>,
+    pub recurrence: Option<String>,
+    pub worked: Vec<WorkedRecord>,
+    pub history: Vec<StateChangeRecord>,
+    pub tags: Vec<String>,
+    pub pinned: bool,
+    pub archived: bool,
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkedRecord {
+    pub start: String,
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StateChangeRecord {
+    pub state: String,
+    pub at: String,
+}
+
+const JOT_HEADERS: [&str; 19] = [
+    "id",
+    "uuid",
+    "created",
+    "type",
+    "day",
+    "state",
+    "state_since",
+    "completed",
+    "reason",
+    "due",
+    "priority",
+    "recurrence",
+    "worked",
+    "history",
+    "tags",
+    "pinned",
+    "archived",
+    "path",
+    "message",
+];
+
+impl From<&Jot> for JotRecord {
+    fn from(jot: &Jot) -> JotRecord {
+        let mut record = JotRecord {
+            id: Some(jot.id).filter(|id| *id != 0),
+            uuid: jot.uuid.clone(),
+            created: jot.datetime.to_rfc3339(),
+            jot_type: jot.type_name().to_string(),
+            day: None,
+            state: None,
+            state_since: None,
+            completed: None,
+            reason: None,
+            due: None,
+            priority: None,
+            recurrence: None,
+            worked: vec![],
+            history: vec![],
+            tags: itertools::sorted(jot.tags.iter().cloned()).collect(),
+            pinned: jot.pinned,
+            archived: jot.archived,
+            path: jot.path.to_string_lossy().to_string(),
+            message: jot.message.clone(),
+        };
+        match &jot.msg_type {
+            MessageType::Note | MessageType::Custom(_) => {}
+            MessageType::Day(date) => record.day = Some(date.to_string()),
+            MessageType::Todo(todo) => {
+                record.state = Some(todo.state.name().to_string());
+                record.state_since = todo.state.since().map(|since| since.to_rfc3339());
+                if let TodoState::Done(completed) = todo.state {
+                    record.completed = Some(completed.to_rfc3339());
+                }
+                if let TodoState::Blocked(_, reason) = &todo.state {
+                    record.reason = Some(reason.clone());
+                }
+                record.due = todo.due.map(|due| due.to_rfc3339());
+                record.priority = todo.priority.map(|p| p.as_str().to_string());
+                record.recurrence = todo.recurrence.map(|rule| rule.to_string());
+                record.worked = todo
+                    .worked
+                    .iter()
+                    .map(|interval| WorkedRecord {
+                        start: interval.start.to_rfc3339(),
+                        end: interval.end.map(|end| end.to_rfc3339()),
+                    })
+                    .collect();
+                record.history = todo
+                    .history
+                    .iter()
+                    .map(|change| StateChangeRecord {
+                        state: change.state.clone(),
+                        at: change.at.to_rfc3339(),
+                    })
+                    .collect();
+            }
+        }
+        record
+    }
+}
+
+impl JotRecord {
+    /// The record as a row of text, in the same order as `JOT_HEADERS`.
+    fn row(&self) -> Vec<String> {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        vec![
+            self.id.map(|id| id.to_string()).unwrap_or_default(),
+            text(&self.uuid),
+            self.created.clone(),
+            self.jot_type.clone(),
+            text(&self.day),
+            text(&self.state),
+            text(&self.state_since),
+            text(&self.completed),
+            text(&self.reason),
+            text(&self.due),
+            text(&self.priority),
+            text(&self.recurrence),
+            // The same start/end,start/end layout we use in the header.
+            self.worked
+                .iter()
+                .map(|interval| format!("{}/{}", interval.start, text(&interval.end)))
+                .collect::<Vec<_>>()
+                .join(","),
+            self.history
+                .iter()
+                .map(|change| format!("{}@{}", change.state, change.at))
+                .collect::<Vec<_>>()
+                .join(","),
+            self.tags.join(" "),
+            self.pinned.to_string(),
+            self.archived.to_string(),
+            self.path.clone(),
+            self.message.clone(),
+        ]
+    }
+}
+
+/// How many notes and todos use a tag, for `jot tags`.
+#[derive(Debug, Serialize)]
+pub struct TagRecord {
+    pub tag: String,
+    pub notes: usize,
+    pub todos: usize,
+}
+
+impl TagRecord {
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.tag.clone(),
+            self.notes.to_string(),
+            self.todos.to_string(),
+        ]
+    }
+}
+
+pub fn write_jots(jots: &[Jot], format: Format) -> Result<()> {
+    let records = jots.iter().map(JotRecord::from).collect::<Vec<_>>();
+    write_records(
+        &records,
+        &JOT_HEADERS,
+        |record: &JotRecord| record.row(),
+        format,
+    )
+}
+
+pub fn write_tags(tags: &[TagRecord], format: Format) -> Result<()> {
+    write_records(tags, &["tag", "notes", "todos"], TagRecord::row, format)
+}
+
+fn write_records<T: Serialize>(
+    records: &[T],
+    headers: &[&str],
+    row: impl Fn(&T) -> Vec<String>,
+    format: Format,
+) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(records)?)?,
+        Format::Ndjson => {
+            for record in records {
+                writeln!(out, "{}", serde_json::to_string(record)?)?;
+            }
+        }
+        Format::Csv | Format::Tsv => {
+            let escape = if format == Format::Csv {
+                csv_field
+            } else {
+                tsv_field
+            };
+            let separator = if format == Format::Csv { "," } else { "\t" };
+            let headers = headers.iter().map(|header| header.to_string()).collect();
+            for fields in std::iter::once(headers).chain(records.iter().map(row)) {
+                let fields = fields.iter().map(|field| escape(field)).collect::<Vec<_>>();
+                writeln!(out, "{}", fields.join(separator))?;
+            }
+        }
+        Format::Pretty => unreachable!("pretty output is printed by the commands themselves"),
+    }
+    Ok(())
+}
+
+/// Quote a field if it needs it, doubling any quotes inside (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// TSV can't quote so tabs and newlines are escaped instead.
+fn tsv_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[test]
+fn test_fields() {
+    assert_eq!(csv_field("plain"), "plain");
+    assert_eq!(csv_field("a, \"b\"\nc"), "\"a, \"\"b\"\"\nc\"");
+    assert_eq!(tsv_field("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+}
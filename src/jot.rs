@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use chrono::prelude::*;
 use std::fs::File;
 use std::io::prelude::*;
@@ -10,10 +10,14 @@ use itertools::Itertools;
 use regex::Regex;
 use std::collections::HashSet;
 
+use crate::checklist;
 use crate::config;
+use crate::config::JotType;
 use crate::constants::*;
+use crate::output;
+use crate::recurrence::Recurrence;
 use crate::utils;
-use crate::utils::{count_real_chars, pluralize_time_unit, pretty_duration};
+use crate::utils::{count_real_chars, pluralize_time_unit, pretty_duration, pretty_work_duration};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Jot {
@@ -22,9 +26,14 @@ pub struct Jot {
     pub msg_type: MessageType,
     // TODO: These two fields aren't needed for creating new jots but are only when it is read.
     //       Maybe we should make a ReadJot super type?
+    // Zero for jots that don't have a numeric id, new and archived ones.
     pub id: usize,
     pub uuid: Option<String>,
     pub tags: HashSet<String>,
+    // Pinned jots are shown first so they are always at hand.
+    pub pinned: bool,
+    // Archived jots live in their own folder and are hidden by default.
+    pub archived: bool,
     // The path to the jot on disk.
     pub path: PathBuf,
 }
@@ -32,31 +41,185 @@ pub struct Jot {
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum MessageType {
     Note,
+    Todo(Todo),
+    // A running journal entry for a single day.
+    Day(NaiveDate),
+    // One of the types defined in the config.
+    Custom(JotType),
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct Todo {
+    pub state: TodoState,
+    pub due: Option<DateTime<Local>>,
+    pub priority: Option<Priority>,
+    pub recurrence: Option<Recurrence>,
+    pub worked: Vec<WorkInterval>,
+    // Every state the todo has moved into, oldest first.
+    pub history: Vec<StateChange>,
+}
+
+/// A stretch of time spent working on a todo, if there is no end we're still at it.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct WorkInterval {
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+}
+
+impl WorkInterval {
+    /// How much of this interval falls between start and end.
+    pub fn overlap(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        now: DateTime<Local>,
+    ) -> chrono::Duration {
+        let overlap_start = std::cmp::max(self.start, start);
+        let overlap_end = std::cmp::min(self.end.unwrap_or(now), end);
+        std::cmp::max(overlap_end - overlap_start, chrono::Duration::zero())
+    }
+}
+
+/// A todo moving into a state, kept so earlier transitions aren't lost.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct StateChange {
+    pub state: String,
+    pub at: DateTime<Local>,
+}
 
-    // Completed date, if not present we haven't completed yet.
-    Todo(Option<DateTime<Local>>),
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    /// Parse a priority from a string, accepting the first letter as a shorthand.
+    pub fn from_string(i: &str) -> Option<Priority> {
+        match i.to_ascii_lowercase().as_str() {
+            "low" | "l" => Some(Priority::Low),
+            "medium" | "med" | "m" => Some(Priority::Medium),
+            "high" | "h" => Some(Priority::High),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+}
+
+/// Where a todo is in its workflow, along with when it got there.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub enum TodoState {
+    #[default]
+    Open,
+    InProgress(DateTime<Local>),
+    // Along with the reason it's blocked.
+    Blocked(DateTime<Local>, String),
+    Waiting(DateTime<Local>),
+    Cancelled(DateTime<Local>),
+    // Completed date.
+    Done(DateTime<Local>),
+}
+
+impl TodoState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TodoState::Open => TODO_STATE_OPEN,
+            TodoState::InProgress(_) => TODO_STATE_IN_PROGRESS,
+            TodoState::Blocked(_, _) => TODO_STATE_BLOCKED,
+            TodoState::Waiting(_) => TODO_STATE_WAITING,
+            TodoState::Cancelled(_) => TODO_STATE_CANCELLED,
+            TodoState::Done(_) => TODO_STATE_DONE,
+        }
+    }
+
+    /// When the todo moved into this state, an open todo has no transition.
+    pub fn since(&self) -> Option<DateTime<Local>> {
+        match self {
+            TodoState::Open => None,
+            TodoState::InProgress(since)
+            | TodoState::Blocked(since, _)
+            | TodoState::Waiting(since)
+            | TodoState::Cancelled(since)
+            | TodoState::Done(since) => Some(*since),
+        }
+    }
+
+    /// Done and cancelled todos don't need any more attention.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, TodoState::Done(_) | TodoState::Cancelled(_))
+    }
+}
+
+impl Todo {
+    /// A todo is overdue if it hasn't been finished and its due date has passed.
+    pub fn is_overdue(&self, now: DateTime<Local>) -> bool {
+        !self.state.is_finished() && self.due.map(|due| due < now).unwrap_or(false)
+    }
+
+    /// Move into a new state, remembering when we did.
+    pub fn set_state(&mut self, state: TodoState, now: DateTime<Local>) {
+        self.history.push(StateChange {
+            state: state.name().to_string(),
+            at: now,
+        });
+        self.state = state;
+    }
+
+    /// The interval we're currently working in, if any.
+    pub fn running_interval(&mut self) -> Option<&mut WorkInterval> {
+        self.worked
+            .iter_mut()
+            .find(|interval| interval.end.is_none())
+    }
+
+    /// Total time spent working on this todo.
+    pub fn time_worked(&self, now: DateTime<Local>) -> chrono::Duration {
+        self.worked
+            .iter()
+            .map(|interval| interval.end.unwrap_or(now) - interval.start)
+            .fold(chrono::Duration::zero(), |total, worked| total + worked)
+    }
 }
 
 impl Jot {
     pub fn new(
         path: &Path,
+        datetime: DateTime<Local>,
         message: &str,
         message_type: MessageType,
         previous_uuids: &HashSet<String>,
     ) -> Jot {
-        let local: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
-
         Jot {
-            datetime: local,
+            datetime,
             message: message.trim().to_string(),
             msg_type: message_type,
             id: 0,
             uuid: Some(utils::generate_new_uuid(previous_uuids)), // todo replace with randomize fn, we need to know all previous
-            tags: HashSet::new(),
+            tags: find_tags(message),
+            pinned: false,
+            archived: false,
             path: path.to_owned(),
         }
     }
 
+    /// What kind of jot this is, as written on the command line.
+    pub fn type_name(&self) -> &str {
+        match &self.msg_type {
+            MessageType::Note => NOTE,
+            MessageType::Todo(_) => TODO,
+            MessageType::Day(_) => DAY,
+            MessageType::Custom(jot_type) => &jot_type.name,
+        }
+    }
+
     pub fn pprint(&self) {
         let msg = crate::utils::break_apart_long_string(&self.message.clone());
         self.pprint_with_custom_msg(Some(&msg));
@@ -65,39 +228,175 @@ impl Jot {
     /// Pretty print a Jot, we need to support custom messages for
     /// highlighting (such as via grep).
     pub fn pprint_with_custom_msg(&self, msg_override: Option<&str>) {
+        print!("{}", self.render_with_custom_msg(msg_override));
+    }
+
+    /// The pretty printed jot, boxed up and colored for a terminal or plain
+    /// text for files and other programs.
+    pub fn render_with_custom_msg(&self, msg_override: Option<&str>) -> String {
         let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
-        let time_difference = now - self.datetime;
-        let (amount, amount_unit) = pretty_duration(time_difference);
-        let plural_amount_unit = pluralize_time_unit(amount, amount_unit);
-        let header_string = match self.msg_type {
-            MessageType::Todo(None) => format!(
-                "{} {} {} ago",
-                TODO.magenta().bold(),
-                amount.to_string().bold().blue(),
-                plural_amount_unit
-            ),
-            MessageType::Todo(Some(completed_date)) => {
-                let time_difference = now - completed_date;
+        let age = pretty_age(self.datetime, now);
+        let header_string = match &self.msg_type {
+            MessageType::Todo(
+                todo @ Todo {
+                    state: TodoState::Done(completed_date),
+                    ..
+                },
+            ) => {
+                let time_difference = now - *completed_date;
                 let (amount, amount_unit) = pretty_duration(time_difference);
                 let plural_amount_unit = pluralize_time_unit(amount, amount_unit);
 
-                format!(
+                let mut header_string = format!(
                     "{} completed {} {} ago",
-                    TODO.green().bold(),
+                    self.type_label(now),
+                    amount.to_string().bold().blue(),
+                    plural_amount_unit
+                );
+                if !todo.worked.is_empty() {
+                    header_string.push_str(&format!(
+                        " worked {}",
+                        pretty_work_duration(todo.time_worked(now))
+                    ));
+                }
+                header_string
+            }
+            MessageType::Todo(
+                todo @ Todo {
+                    state: TodoState::Cancelled(cancelled_date),
+                    ..
+                },
+            ) => {
+                let time_difference = now - *cancelled_date;
+                let (amount, amount_unit) = pretty_duration(time_difference);
+                let plural_amount_unit = pluralize_time_unit(amount, amount_unit);
+
+                let mut header_string = format!(
+                    "{} cancelled {} {} ago",
+                    self.type_label(now),
                     amount.to_string().bold().blue(),
                     plural_amount_unit
-                )
+                );
+                if !todo.worked.is_empty() {
+                    header_string.push_str(&format!(
+                        " worked {}",
+                        pretty_work_duration(todo.time_worked(now))
+                    ));
+                }
+                header_string
             }
-            MessageType::Note => format!(
-                "{} {} {} ago",
-                NOTE.blue().bold(),
-                amount.to_string().bold().blue(),
-                plural_amount_unit
+            MessageType::Todo(
+                todo @ Todo {
+                    state,
+                    due,
+                    priority,
+                    recurrence,
+                    worked,
+                    ..
+                },
+            ) => {
+                let overdue = due.map(|due| due < now).unwrap_or(false);
+                let mut header_string = format!("{} {}", self.type_label(now), age);
+
+                if let Some(since) = state.since() {
+                    let (state_amount, state_unit) = pretty_duration(now - since);
+                    let plural_state_unit = pluralize_time_unit(state_amount, state_unit);
+                    let state_name = match state {
+                        TodoState::Blocked(_, _) => state.name().red().bold(),
+                        _ => state.name().yellow().bold(),
+                    };
+                    header_string.push_str(&format!(
+                        " {} {} {}",
+                        state_name,
+                        state_amount.to_string().bold().blue(),
+                        plural_state_unit
+                    ));
+                    if let TodoState::Blocked(_, reason) = state {
+                        header_string.push_str(&format!(" ({})", reason));
+                    }
+                }
+
+                if let Some(due) = due {
+                    let (due_amount, due_unit) =
+                        pretty_duration(if overdue { now - *due } else { *due - now });
+                    let plural_due_unit = pluralize_time_unit(due_amount, due_unit);
+                    if overdue {
+                        header_string.push_str(&format!(
+                            " {} {} {}",
+                            "overdue".red().bold(),
+                            due_amount.to_string().bold().red(),
+                            plural_due_unit
+                        ));
+                    } else {
+                        header_string.push_str(&format!(
+                            " due in {} {}",
+                            due_amount.to_string().bold().blue(),
+                            plural_due_unit
+                        ));
+                    }
+                }
+
+                if let Some(priority) = priority {
+                    let marker = format!("!{}", priority.as_str());
+                    let marker = match priority {
+                        Priority::High => marker.red().bold(),
+                        Priority::Medium => marker.yellow().bold(),
+                        Priority::Low => marker.dim(),
+                    };
+                    header_string.push_str(&format!(" {}", marker));
+                }
+
+                if let Some(recurrence) = recurrence {
+                    header_string.push_str(&format!(" repeats {}", recurrence));
+                }
+
+                if !worked.is_empty() {
+                    header_string.push_str(&format!(
+                        " worked {}",
+                        pretty_work_duration(todo.time_worked(now))
+                    ));
+                    if worked.iter().any(|interval| interval.end.is_none()) {
+                        header_string.push_str(&format!(" {}", "(running)".green().bold()));
+                    }
+                }
+
+                header_string
+            }
+            MessageType::Note | MessageType::Custom(_) => {
+                format!("{} {}", self.type_label(now), age)
+            }
+            MessageType::Day(date) => format!(
+                "{} {}",
+                self.type_label(now),
+                date.format(DAY_DISPLAY_FORMAT).to_string().bold()
             ),
         };
 
         let msg = msg_override.unwrap_or(&self.message).trim();
 
+        let header_string = match checklist::progress(&self.message) {
+            Some((checked, total)) => {
+                let progress = format!("{}/{}", checked, total);
+                let progress = if checked == total {
+                    progress.green().bold()
+                } else {
+                    progress.yellow().bold()
+                };
+                format!("{} {}", header_string, progress)
+            }
+            None => header_string,
+        };
+        let header_string = if self.pinned {
+            format!("{} {}", header_string, PINNED.yellow().bold())
+        } else {
+            header_string
+        };
+        let header_string = if self.archived {
+            format!("{} {}", header_string, ARCHIVED.dim())
+        } else {
+            header_string
+        };
+
         let header = format!(
             "{} [{}]",
             header_string,
@@ -116,34 +415,88 @@ impl Jot {
         );
 
         let header_chars = count_real_chars(&header).unwrap_or(0);
-        let s_header = std::iter::repeat("─")
-            .take(std::cmp::max(0, bar_length as i64 - header_chars as i64 - 2) as usize)
-            .collect::<String>();
+        let s_header =
+            "─".repeat(std::cmp::max(0, bar_length as i64 - header_chars as i64 - 2) as usize);
 
-        let s = std::iter::repeat("─")
-            .take(count_real_chars(&s_header).unwrap_or(0) + header_chars)
-            .collect::<String>();
+        let s = "─".repeat(count_real_chars(&s_header).unwrap_or(0) + header_chars);
 
         // Make any tags be bold.
         // TODO: We should probably move greps into here as well, right now it is in the view
         // command and that's not where it should live IMO.
         let mut tag_msg = msg.to_string();
-        let found = TAG_RE.find_iter(&msg).collect::<Vec<_>>().into_iter().rev();
+        let found = TAG_RE.find_iter(msg).collect::<Vec<_>>().into_iter().rev();
         for m in found {
             let highlighted = &tag_msg[m.start()..m.end()].to_string().bold();
             tag_msg.replace_range(m.start()..m.end(), &highlighted.to_string());
         }
 
-        println!("{}{}{}{}", "┌─", header, s_header, "─┐");
-        println!("{}", tag_msg);
-        println!("{}{}{}", "└─", s, "─┘");
+        let rendered = if output::is_terminal() {
+            format!("┌─{}{}─┐\n{}\n└─{}─┘\n", header, s_header, tag_msg, s)
+        } else {
+            format!("{}\n{}\n", header, tag_msg)
+        };
+        output::paint(&rendered)
+    }
+
+    /// The jot's type colored the way we show it at a glance: red for overdue
+    /// todos, green for finished ones and so on.
+    fn type_label(&self, now: DateTime<Local>) -> String {
+        let label = match &self.msg_type {
+            MessageType::Todo(Todo {
+                state: TodoState::Done(_),
+                ..
+            }) => TODO.green(),
+            MessageType::Todo(Todo {
+                state: TodoState::Cancelled(_),
+                ..
+            }) => TODO.dark_gray(),
+            MessageType::Todo(todo) if todo.is_overdue(now) => TODO.red(),
+            MessageType::Todo(_) => TODO.magenta(),
+            MessageType::Note => NOTE.blue(),
+            MessageType::Day(_) => DAY.yellow(),
+            MessageType::Custom(jot_type) => colorize(&jot_type.name, jot_type.color.as_deref()),
+        };
+        label.bold().to_string()
+    }
+
+    /// The columns for the one line listing: id, type, age, tags and the
+    /// first line of the message.
+    pub fn oneline_columns(&self) -> Vec<String> {
+        let now: DateTime<Local> = Local::now().with_nanosecond(0).unwrap();
+        let tags = itertools::sorted(&self.tags).join(" ");
+        vec![
+            self.uuid
+                .clone()
+                .unwrap_or(self.id.to_string())
+                .cyan()
+                .bold()
+                .to_string(),
+            self.type_label(now),
+            pretty_age(self.datetime, now),
+            tags.bold().to_string(),
+            self.message.lines().next().unwrap_or("").to_string(),
+        ]
     }
 
     /// Write out the header string for this particular note.
     fn write_to_header_string(&self) -> String {
+        let header = self.write_type_to_header_string();
+        if self.pinned {
+            // Any type of jot can be pinned so it goes on the end of the header.
+            format!(
+                "{} {}=true]",
+                header.strip_suffix(']').unwrap_or(&header),
+                PINNED_KEY
+            )
+        } else {
+            header
+        }
+    }
+
+    fn write_type_to_header_string(&self) -> String {
         let date_str = self.datetime.to_rfc3339();
 
-        match self.msg_type {
+        match &self.msg_type {
             MessageType::Note => {
                 if let Some(uuid) = &self.uuid {
                     format!("[{} id={}]", date_str, uuid)
@@ -152,19 +505,86 @@ impl Jot {
                 }
             }
 
-            MessageType::Todo(maybe_completed_date) => {
-                let completed_str = maybe_completed_date
-                    .map(|date| date.to_rfc3339())
-                    .unwrap_or(TODO_NOT_DONE_PLACEHOLDER.to_string());
+            MessageType::Custom(jot_type) => {
                 if let Some(uuid) = &self.uuid {
-                    format!(
-                        "[{} {} {} id={}]",
-                        date_str, TODO_HEADER, completed_str, uuid
-                    )
+                    format!("[{} {} id={}]", date_str, jot_type.header(), uuid)
                 } else {
-                    format!("[{} {} {}]", date_str, TODO_HEADER, completed_str)
+                    format!("[{} {}]", date_str, jot_type.header())
                 }
             }
+
+            MessageType::Day(date) => {
+                let day_str = date.format(DAY_HEADER_FORMAT);
+                if let Some(uuid) = &self.uuid {
+                    format!("[{} {} {} id={}]", date_str, DAY_HEADER, day_str, uuid)
+                } else {
+                    format!("[{} {} {}]", date_str, DAY_HEADER, day_str)
+                }
+            }
+
+            MessageType::Todo(todo) => {
+                // The completed date lives where it always has so older jots stay
+                // readable, every other state is a key along with when we entered it.
+                let completed_str = match todo.state {
+                    TodoState::Done(date) => date.to_rfc3339(),
+                    _ => TODO_NOT_DONE_PLACEHOLDER.to_string(),
+                };
+                let mut header = format!("[{} {} {}", date_str, TODO_HEADER, completed_str);
+                if let Some(uuid) = &self.uuid {
+                    header.push_str(&format!(" id={}", uuid));
+                }
+                match &todo.state {
+                    TodoState::Open | TodoState::Done(_) => {}
+                    state => {
+                        header.push_str(&format!(" {}={}", TODO_STATE_KEY, state.name()));
+                        if let Some(since) = state.since() {
+                            header.push_str(&format!(" {}={}", TODO_SINCE_KEY, since.to_rfc3339()));
+                        }
+                        if let TodoState::Blocked(_, reason) = state {
+                            header.push_str(&format!(
+                                " {}={}",
+                                TODO_REASON_KEY,
+                                quote_header_value(reason)
+                            ));
+                        }
+                    }
+                }
+                if let Some(due) = todo.due {
+                    header.push_str(&format!(" {}={}", TODO_DUE_KEY, due.to_rfc3339()));
+                }
+                if let Some(priority) = todo.priority {
+                    header.push_str(&format!(" {}={}", TODO_PRIORITY_KEY, priority.as_str()));
+                }
+                if let Some(recurrence) = todo.recurrence {
+                    // Header values can't have spaces in them.
+                    let rule = recurrence.to_string().replace(' ', "-");
+                    header.push_str(&format!(" {}={}", TODO_RECURRENCE_KEY, rule));
+                }
+                if !todo.worked.is_empty() {
+                    let intervals = todo
+                        .worked
+                        .iter()
+                        .map(|interval| {
+                            format!(
+                                "{}/{}",
+                                interval.start.to_rfc3339(),
+                                interval.end.map(|end| end.to_rfc3339()).unwrap_or_default()
+                            )
+                        })
+                        .join(",");
+                    header.push_str(&format!(" {}={}", TODO_WORKED_KEY, intervals));
+                }
+                if !todo.history.is_empty() {
+                    let changes = todo
+                        .history
+                        .iter()
+                        .map(|change| format!("{}@{}", change.state, change.at.to_rfc3339()))
+                        .join(",");
+                    header.push_str(&format!(" {}={}", TODO_HISTORY_KEY, changes));
+                }
+                header.push(']');
+                header
+            }
         }
     }
 }
@@ -179,101 +599,583 @@ impl std::fmt::Display for Jot {
 
 impl MessageType {
     /// Parse a message type from a string.
-    fn from_string(i: &str) -> Option<(Option<String>, MessageType)> {
-        let parts: Vec<&str> = i.split_whitespace().collect();
+    fn from_string(i: &str, types: &[JotType]) -> Option<(Option<String>, MessageType)> {
+        let parts = split_header(i);
 
-        let id_part = parts
-            .iter()
-            .find(|p| p.starts_with("id="))
-            .map(|id_part| id_part.split("=").last().unwrap_or("").to_string());
+        let find_value = |key: &str| {
+            let prefix = format!("{}=", key);
+            parts
+                .iter()
+                .find(|p| p.starts_with(&prefix))
+                .map(|part| part[prefix.len()..].to_string())
+        };
+
+        let id_part = find_value("id");
 
-        match *parts.get(0)? {
+        match parts.first()?.as_str() {
             TODO_HEADER => {
                 let date = parts.get(1)?.trim();
-                if date == TODO_NOT_DONE_PLACEHOLDER {
-                    Some((id_part, MessageType::Todo(None)))
+                let state = if date == TODO_NOT_DONE_PLACEHOLDER {
+                    let since = find_value(TODO_SINCE_KEY).and_then(|since| parse_rfc3339(&since));
+                    match (find_value(TODO_STATE_KEY).as_deref(), since) {
+                        (Some(TODO_STATE_IN_PROGRESS), Some(since)) => TodoState::InProgress(since),
+                        (Some(TODO_STATE_BLOCKED), Some(since)) => TodoState::Blocked(
+                            since,
+                            find_value(TODO_REASON_KEY).unwrap_or_default(),
+                        ),
+                        (Some(TODO_STATE_WAITING), Some(since)) => TodoState::Waiting(since),
+                        (Some(TODO_STATE_CANCELLED), Some(since)) => TodoState::Cancelled(since),
+                        _ => TodoState::Open,
+                    }
                 } else {
                     // Attempt to parse the completed date.
+                    TodoState::Done(parse_rfc3339(date)?)
+                };
 
-                    let parsed_date: DateTime<FixedOffset> =
-                        DateTime::parse_from_rfc3339(&date).ok()?;
-                    Some((
-                        id_part,
-                        MessageType::Todo(Some(DateTime::from(parsed_date))),
-                    ))
-                }
+                let due = find_value(TODO_DUE_KEY).and_then(|due| parse_rfc3339(&due));
+                let priority =
+                    find_value(TODO_PRIORITY_KEY).and_then(|p| Priority::from_string(&p));
+                let recurrence = find_value(TODO_RECURRENCE_KEY)
+                    .and_then(|rule| Recurrence::from_string(&rule).ok());
+                let worked = find_value(TODO_WORKED_KEY)
+                    .map(|intervals| {
+                        intervals
+                            .split(',')
+                            .filter_map(|interval| {
+                                let (start, end) = interval.split_once('/')?;
+                                Some(WorkInterval {
+                                    start: parse_rfc3339(start)?,
+                                    end: parse_rfc3339(end),
+                                })
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let history = find_value(TODO_HISTORY_KEY)
+                    .map(|changes| {
+                        changes
+                            .split(',')
+                            .filter_map(|change| {
+                                let (state, at) = change.split_once('@')?;
+                                Some(StateChange {
+                                    state: state.to_string(),
+                                    at: parse_rfc3339(at)?,
+                                })
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                Some((
+                    id_part,
+                    MessageType::Todo(Todo {
+                        state,
+                        due,
+                        priority,
+                        recurrence,
+                        worked,
+                        history,
+                    }),
+                ))
+            }
+            DAY_HEADER => {
+                let date = NaiveDate::parse_from_str(parts.get(1)?, DAY_HEADER_FORMAT).ok()?;
+                Some((id_part, MessageType::Day(date)))
+            }
+            // Notes don't have a keyword, anything else is a custom type. We hang on
+            // to types that aren't in the config so we don't lose them on a rewrite,
+            // `check_header` is the one that insists on a configured type.
+            keyword if !keyword.contains('=') => {
+                let jot_type = types
+                    .iter()
+                    .find(|jot_type| jot_type.header() == keyword)
+                    .cloned()
+                    .unwrap_or_else(|| JotType::unknown(keyword));
+                Some((id_part, MessageType::Custom(jot_type)))
             }
             _ => Some((id_part, MessageType::Note)),
         }
     }
 }
 
+/// Split a header up on whitespace, values can be quoted to keep their spaces
+/// (e.g. `reason="waiting on review"`).
+fn split_header(i: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut in_quotes = false;
+    let mut chars = i.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => part.extend(chars.next()),
+            c if c.is_whitespace() && !in_quotes => {
+                if !part.is_empty() {
+                    parts.push(std::mem::take(&mut part));
+                }
+            }
+            c => part.push(c),
+        }
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+/// Quote a header value so it survives `split_header`.
+fn quote_header_value(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', " ")
+    )
+}
+
+#[test]
+fn test_split_header() {
+    assert_eq!(
+        split_header(r#"todo not-done id=kw reason="on \"infra\" [team]"  x"#),
+        vec![
+            "todo",
+            "not-done",
+            "id=kw",
+            r#"reason=on "infra" [team]"#,
+            "x"
+        ]
+    );
+    let reason = r#"a "quoted" \ thing"#;
+    assert_eq!(
+        split_header(&format!("reason={}", quote_header_value(reason))),
+        vec![format!("reason={}", reason)]
+    );
+}
+
+/// How long ago something happened, or how long until it happens for jots
+/// dated in the future (e.g. "3 days ago" or "in 2 hours").
+fn pretty_age(then: DateTime<Local>, now: DateTime<Local>) -> String {
+    let future = then > now;
+    let (amount, amount_unit) = pretty_duration(if future { then - now } else { now - then });
+    let plural_amount_unit = pluralize_time_unit(amount, amount_unit);
+    if future {
+        format!(
+            "in {} {}",
+            amount.to_string().bold().blue(),
+            plural_amount_unit
+        )
+    } else {
+        format!(
+            "{} {} ago",
+            amount.to_string().bold().blue(),
+            plural_amount_unit
+        )
+    }
+}
+
+/// Color some text with one of the basic terminal colors by name.
+fn colorize(text: &str, color: Option<&str>) -> colorful::core::color_string::CString {
+    match color.unwrap_or("blue") {
+        "black" => text.black(),
+        "red" => text.red(),
+        "green" => text.green(),
+        "yellow" => text.yellow(),
+        "magenta" => text.magenta(),
+        "cyan" => text.cyan(),
+        "white" => text.white(),
+        "gray" | "grey" => text.light_gray(),
+        _ => text.blue(),
+    }
+}
+
+fn parse_rfc3339(date: &str) -> Option<DateTime<Local>> {
+    let parsed_date: DateTime<FixedOffset> = DateTime::parse_from_rfc3339(date).ok()?;
+    Some(DateTime::from(parsed_date))
+}
+
 pub fn stream_jots(config: config::Config, reversed: bool) -> Result<impl Iterator<Item = Jot>> {
+    stream_all_jots(config, reversed, false)
+}
+
+/// Stream the journal, optionally along with the jots that have been archived.
+pub fn stream_all_jots(
+    config: config::Config,
+    reversed: bool,
+    include_archived: bool,
+) -> Result<impl Iterator<Item = Jot>> {
     assert!(config.journal_path.is_dir());
 
-    let mut dirs = std::fs::read_dir(config.journal_path)?
+    let types = config.types.clone();
+    let archive_path = config::archive_path(&config);
+    let mut dirs = std::fs::read_dir(&config.journal_path)?
         .map(|entry| Ok(entry?.path()))
         .collect::<Result<Vec<_>>>()?;
+    if include_archived && archive_path.is_dir() {
+        for entry in std::fs::read_dir(&archive_path)? {
+            dirs.push(entry?.path());
+        }
+    }
 
+    // Archived jots are mixed back in where they were written.
     if reversed {
-        dirs.sort_by(|a, b| b.cmp(a));
+        dirs.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
     } else {
-        dirs.sort();
+        dirs.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
     }
 
     // TODO: We can parallelize this.
     let jot_stream = dirs
         .into_iter()
-        .filter(|entry| entry.is_file())
-        .filter_map(|file_path| {
+        // Anything else someone (or an editor) left in the journal isn't a jot.
+        .filter(|entry| {
+            entry.is_file() && entry.extension().and_then(|ext| ext.to_str()) == Some(JOT_EXTENSION)
+        })
+        .filter_map(move |file_path| {
             // Load the file
             let mut file = File::open(&file_path).ok()?;
             let mut contents = String::new();
             file.read_to_string(&mut contents).ok()?;
-            let lines = contents.lines().collect::<Vec<_>>();
-            let header_line = lines.first()?;
-            let message = lines.iter().skip(1).join("\n");
-            Some(parse_jot(header_line, &message, &file_path)?)
+            let mut jot = parse_jot_contents(&contents, &file_path, &types)?;
+            jot.archived = file_path.parent() == Some(archive_path.as_path());
+            Some(jot)
         })
-        // Give each jot a real ID based on its position in the journal.
-        .zip(1..)
-        .map(|(mut jot, index)| {
-            jot.id = index;
-            jot
+        // Give each jot a real ID based on its position in the journal. Archived
+        // jots go by their uuid so mixing them in doesn't shift the ids.
+        .scan(0, |next_id, mut jot| {
+            if !jot.archived {
+                *next_id += 1;
+                jot.id = *next_id;
+            }
+            Some(jot)
         });
 
     Ok(jot_stream)
 }
 
 lazy_static! {
-    static ref TAG_RE: Regex = Regex::new(r"@[a-zA-Z][0-9a-zA-Z_]*").unwrap();
+    pub static ref TAG_RE: Regex = Regex::new(r"@[a-zA-Z][0-9a-zA-Z_]*").unwrap();
+    // Stop at the first `]` that isn't inside a quoted value like a blocked reason.
+    static ref HEADER_RE: Regex = Regex::new(
+        r#"\[(\d\d\d\d\-\d\d\-\d\dT\d\d:\d\d:\d\d[-+]\d\d:\d\d)((?:[^\]"]|"(?:[^"\\]|\\.)*")*?)\].*"#
+    )
+    .unwrap();
+}
+
+/// All of the tags (`@thing`) mentioned in a message.
+pub fn find_tags(message: &str) -> HashSet<String> {
+    TAG_RE
+        .find_iter(message)
+        .map(|tag| tag.as_str().to_owned())
+        .collect()
+}
+
+/// Parse a whole jot file, the header line followed by the message.
+pub fn parse_jot_contents(contents: &str, path: &Path, types: &[JotType]) -> Option<Jot> {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let header_line = lines.first()?;
+    let message = lines.iter().skip(1).join("\n");
+    parse_jot(header_line, &message, path, types)
 }
 
 /// Parse a line in our jot log.
-fn parse_jot(header_line: &str, message: &str, path: &Path) -> Option<Jot> {
-    lazy_static! {
-        static ref RE: Regex =
-            Regex::new(r"\[(\d\d\d\d\-\d\d\-\d\dT\d\d:\d\d:\d\d-\d\d:\d\d)(.*?)\].*").unwrap();
-    }
-    let caps = RE.captures(header_line)?;
+fn parse_jot(header_line: &str, message: &str, path: &Path, types: &[JotType]) -> Option<Jot> {
+    let caps = HEADER_RE.captures(header_line)?;
     let date = caps.get(1)?.as_str().trim().to_owned();
     let message_type = caps.get(2).map(|m| m.as_str()).unwrap_or("").trim();
 
-    let tags = TAG_RE
-        .find_iter(message)
-        .map(|tag| tag.as_str().to_owned())
-        .collect();
+    let tags = find_tags(message);
 
     let parsed_date: DateTime<FixedOffset> = DateTime::parse_from_rfc3339(&date).ok()?;
     let (id, msg_type) =
-        MessageType::from_string(&message_type).unwrap_or((None, MessageType::Note));
+        MessageType::from_string(message_type, types).unwrap_or((None, MessageType::Note));
+    let pinned = split_header(message_type)
+        .iter()
+        .any(|part| *part == format!("{}=true", PINNED_KEY));
     Some(Jot {
         datetime: DateTime::from(parsed_date),
         message: message.trim().to_string(),
         tags,
+        pinned,
+        archived: false,
         id: 0,
         uuid: id,
-        msg_type: msg_type,
+        msg_type,
         path: path.to_owned(),
     })
 }
+
+#[test]
+fn test_parse_todo_header() {
+    let jot = parse_jot(
+        "[2020-04-10T21:58:21-05:00 todo not-done id=kw due=2020-04-17T00:00:00-05:00 priority=high pinned=true]",
+        "pay rent @home",
+        Path::new("00001586573901.jot"),
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(jot.uuid, Some("kw".to_string()));
+    assert!(jot.tags.contains("@home"));
+    assert!(jot.pinned);
+    match &jot.msg_type {
+        MessageType::Todo(todo) => {
+            assert_eq!(todo.state, TodoState::Open);
+            assert_eq!(todo.priority, Some(Priority::High));
+            assert_eq!(
+                todo.due,
+                Some(DateTime::from(
+                    DateTime::parse_from_rfc3339("2020-04-17T00:00:00-05:00").unwrap()
+                ))
+            );
+        }
+        _ => panic!("expected a todo"),
+    }
+
+    // Writing the jot back out should give us the same header.
+    assert_eq!(
+        parse_jot(
+            jot.write_to_header_string().as_str(),
+            &jot.message,
+            &jot.path,
+            &[]
+        ),
+        Some(jot)
+    );
+}
+
+#[test]
+fn test_parse_unknown_type() {
+    // A type that's been taken out of the config survives being written back out.
+    let jot = parse_jot(
+        "[2020-04-10T21:58:21-05:00 idea id=tr]",
+        "a thought",
+        Path::new("00001586573901.jot"),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(jot.type_name(), "idea");
+    assert_eq!(
+        parse_jot(
+            jot.write_to_header_string().as_str(),
+            &jot.message,
+            &jot.path,
+            &[]
+        ),
+        Some(jot)
+    );
+}
+
+#[test]
+fn test_parse_todo_history() {
+    // A `]` in a quoted reason doesn't end the header, the one after it does.
+    let jot = parse_jot(
+        r#"[2020-04-10T21:58:21-05:00 todo not-done id=kw state=blocked since=2020-04-12T09:00:00-05:00 reason="on [infra]" history=in-progress@2020-04-11T09:00:00-05:00,blocked@2020-04-12T09:00:00-05:00] trailing ]"#,
+        "pay rent",
+        Path::new("00001586573901.jot"),
+        &[],
+    )
+    .unwrap();
+
+    match &jot.msg_type {
+        MessageType::Todo(todo) => {
+            assert_eq!(
+                todo.history
+                    .iter()
+                    .map(|change| change.state.as_str())
+                    .collect::<Vec<_>>(),
+                vec![TODO_STATE_IN_PROGRESS, TODO_STATE_BLOCKED]
+            );
+            assert_eq!(
+                todo.state,
+                TodoState::Blocked(todo.history[1].at, "on [infra]".to_string())
+            );
+        }
+        _ => panic!("expected a todo"),
+    }
+
+    assert_eq!(
+        parse_jot(
+            jot.write_to_header_string().as_str(),
+            &jot.message,
+            &jot.path,
+            &[]
+        ),
+        Some(jot)
+    );
+}
+
+/// Check a header line strictly. The parser quietly skips anything it doesn't
+/// understand, which is what we want when reading the journal but not when
+/// saving a header someone just edited by hand.
+pub fn check_header(header_line: &str, types: &[JotType]) -> Result<()> {
+    let caps = HEADER_RE.captures(header_line).ok_or_else(|| {
+        anyhow!("the first line should be a header like [2020-04-10T21:58:21-05:00 id=kw]")
+    })?;
+    parse_rfc3339(&caps[1]).ok_or_else(|| anyhow!("{:?} isn't a valid date", &caps[1]))?;
+
+    let parts = split_header(&caps[2]);
+    let keyword = parts.first().map(String::as_str).unwrap_or("");
+    let (is_todo, skip) = match keyword {
+        TODO_HEADER => {
+            let completed = parts.get(1).map(String::as_str).unwrap_or("");
+            if completed != TODO_NOT_DONE_PLACEHOLDER && parse_rfc3339(completed).is_none() {
+                bail!(
+                    "a todo needs {:?} or the date it was completed after {:?}",
+                    TODO_NOT_DONE_PLACEHOLDER,
+                    TODO_HEADER
+                );
+            }
+            (true, 2)
+        }
+        DAY_HEADER => {
+            let date = parts.get(1).map(String::as_str).unwrap_or("");
+            NaiveDate::parse_from_str(date, DAY_HEADER_FORMAT)
+                .map_err(|_| anyhow!("{:?} isn't a valid date for a day", date))?;
+            (false, 2)
+        }
+        keyword if !keyword.is_empty() && !keyword.contains('=') => {
+            if !types.iter().any(|jot_type| jot_type.header() == keyword) {
+                bail!(
+                    "{:?} isn't a type of jot, use {:?}, {:?} or one of the types in the config",
+                    keyword,
+                    TODO_HEADER,
+                    DAY_HEADER
+                );
+            }
+            (false, 1)
+        }
+        _ => (false, 0),
+    };
+
+    for part in parts.iter().skip(skip) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected key=value in the header but found {:?}", part))?;
+        let valid = match key {
+            "id" => !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric()),
+            PINNED_KEY => value == "true" || value == "false",
+            TODO_DUE_KEY | TODO_SINCE_KEY if is_todo => parse_rfc3339(value).is_some(),
+            TODO_PRIORITY_KEY if is_todo => Priority::from_string(value).is_some(),
+            TODO_RECURRENCE_KEY if is_todo => Recurrence::from_string(value).is_ok(),
+            // Open and done are written without a state key.
+            TODO_STATE_KEY if is_todo => {
+                TODO_STATES.contains(&value) && value != TODO_STATE_OPEN && value != TODO_STATE_DONE
+            }
+            TODO_REASON_KEY if is_todo => true,
+            TODO_WORKED_KEY if is_todo => {
+                value
+                    .split(',')
+                    .all(|interval| match interval.split_once('/') {
+                        Some((start, end)) => {
+                            parse_rfc3339(start).is_some()
+                                && (end.is_empty() || parse_rfc3339(end).is_some())
+                        }
+                        None => false,
+                    })
+            }
+            TODO_HISTORY_KEY if is_todo => {
+                value.split(',').all(|change| match change.split_once('@') {
+                    Some((state, at)) => {
+                        TODO_STATES.contains(&state) && parse_rfc3339(at).is_some()
+                    }
+                    None => false,
+                })
+            }
+            _ => bail!("{:?} doesn't belong in this header", key),
+        };
+        if !valid {
+            bail!("{:?} isn't a valid {}", value, key);
+        }
+    }
+
+    // The parser quietly treats a state it can't make sense of as open.
+    if is_todo {
+        let has_key = |key: &str| {
+            let prefix = format!("{}=", key);
+            parts
+                .iter()
+                .skip(skip)
+                .any(|part| part.starts_with(&prefix))
+        };
+        let done = parts.get(1).map(String::as_str) != Some(TODO_NOT_DONE_PLACEHOLDER);
+        if has_key(TODO_STATE_KEY) && done {
+            bail!(
+                "a completed todo can't have a {}, use {:?} to give it one",
+                TODO_STATE_KEY,
+                TODO_NOT_DONE_PLACEHOLDER
+            );
+        }
+        if has_key(TODO_STATE_KEY) && !has_key(TODO_SINCE_KEY) {
+            bail!(
+                "a todo with a {} needs {} saying when it got there",
+                TODO_STATE_KEY,
+                TODO_SINCE_KEY
+            );
+        }
+        if has_key(TODO_SINCE_KEY) && !has_key(TODO_STATE_KEY) {
+            bail!("{} only goes with a {}", TODO_SINCE_KEY, TODO_STATE_KEY);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_check_header() {
+    let types = vec![JotType {
+        name: "idea".to_string(),
+        plural: "ideas".to_string(),
+        header: None,
+        color: None,
+    }];
+    assert!(check_header(
+        "[2020-04-10T21:58:21-05:00 todo not-done id=kw due=2020-04-17T00:00:00-05:00 reason=\"a b\"]",
+        &types
+    )
+    .is_ok());
+    assert!(check_header("[2020-04-10T21:58:21-05:00 day 2020-04-10 id=kw]", &types).is_ok());
+    assert!(check_header("[2020-04-10T21:58:21-05:00 idea id=kw]", &types).is_ok());
+    assert!(check_header("[2020-04-10T21:58:21-05:00 id=kw]", &types).is_ok());
+
+    assert!(check_header("2020-04-10T21:58:21-05:00 id=kw", &types).is_err());
+    assert!(check_header("[2020-04-10T21:58:21-05:00]", &types).is_ok());
+    assert!(check_header("[2020-04-10T21:58:21-05:00 id=]", &types).is_err());
+    assert!(check_header("[2020-04-10T21:58:21-05:00 todo done id=kw]", &types).is_err());
+    assert!(check_header(
+        "[2020-04-10T21:58:21-05:00 todo not-done id=kw due=friday]",
+        &types
+    )
+    .is_err());
+    assert!(check_header("[2020-04-10T21:58:21-05:00 id=kw priority=high]", &types).is_err());
+    assert!(check_header("[2020-04-10T21:58:21-05:00 idae id=kw]", &types).is_err());
+    assert!(check_header(
+        "[2020-04-10T21:58:21-05:00 todo not-done id=kw state=blocked since=2020-04-11T09:00:00-05:00 reason=x]",
+        &types
+    )
+    .is_ok());
+    assert!(check_header(
+        "[2020-04-10T21:58:21-05:00 todo not-done id=kw state=blocked reason=x]",
+        &types
+    )
+    .is_err());
+    assert!(check_header(
+        "[2020-04-10T21:58:21-05:00 todo 2020-04-12T09:00:00-05:00 id=kw state=waiting since=2020-04-11T09:00:00-05:00]",
+        &types
+    )
+    .is_err());
+    assert!(check_header(
+        "[2020-04-10T21:58:21-05:00 todo not-done id=kw state=done since=2020-04-11T09:00:00-05:00]",
+        &types
+    )
+    .is_err());
+    assert!(check_header(
+        "[2020-04-10T21:58:21-05:00 todo not-done id=kw history=waiting@2020-04-11T09:00:00-05:00]",
+        &types
+    )
+    .is_ok());
+    assert!(check_header(
+        "[2020-04-10T21:58:21-05:00 todo not-done id=kw history=napping@2020-04-11T09:00:00-05:00]",
+        &types
+    )
+    .is_err());
+}
@@ -4,15 +4,210 @@ extern crate prettytable;
 extern crate lazy_static;
 
 use crate::constants::*;
+use crate::jot::Priority;
 use anyhow::Result;
-use clap::{App, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, SubCommand};
 
+mod autotag;
+mod checklist;
 mod commands;
 mod config;
 mod constants;
+mod format;
 mod jot;
+mod output;
+mod recurrence;
+mod template;
 mod utils;
 
+/// The filters shared by all of the commands that display jots.
+fn view_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    view_args_with_date(date_arg())
+}
+
+/// The view arguments with a different date filter, `cat` has always let
+/// `-d` take more than one value.
+fn view_args_with_date<'a, 'b>(date: Arg<'a, 'b>) -> Vec<Arg<'a, 'b>> {
+    let mut args = filter_args_with_date(date);
+    args.push(
+        Arg::with_name("REVERSE")
+            .short("r")
+            .long("reverse")
+            .help("Reverse the output"),
+    );
+    args.push(
+        Arg::with_name("INCLUDE_ARCHIVED")
+            .long("include-archived")
+            .help("Show archived jots too"),
+    );
+    args.push(
+        Arg::with_name("NO_PAGER")
+            .long("no-pager")
+            .help("Print everything instead of paging long output"),
+    );
+    args.push(
+        Arg::with_name("ONELINE")
+            .long("oneline")
+            .conflicts_with("FORMAT")
+            .help("Show each jot on one line: id, type, age, tags and the start of the message"),
+    );
+    args.push(format_arg());
+    args
+}
+
+/// How to print jots, for people or for other programs.
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("FORMAT")
+        .long("format")
+        .value_name("FORMAT")
+        .takes_value(true)
+        .possible_values(&format::FORMATS)
+        .help("Print as pretty text (the default) or json, ndjson, csv or tsv for other tools")
+}
+
+/// The tag, date and grep filters used to pick out jots.
+fn filter_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    filter_args_with_date(date_arg())
+}
+
+fn filter_args_with_date<'a, 'b>(date: Arg<'a, 'b>) -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("TAG")
+            .short("t")
+            .long("tag")
+            .value_name("TAG")
+            .takes_value(true)
+            .multiple(true)
+            .help("Filter by a tag"),
+        date,
+        Arg::with_name("GREP")
+            .short("g")
+            .long("grep")
+            .value_name("GREP")
+            .takes_value(true)
+            .multiple(true)
+            .help("Filter by contents"),
+    ]
+}
+
+fn date_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("RNG")
+        .short("d")
+        .long("date")
+        .value_name("RNG")
+        .takes_value(true)
+        .multiple(false)
+        .help("Filter by a human date range string")
+}
+
+/// The arguments for commands that change jots picked by their ids, the
+/// view filters or both.
+fn bulk_args<'a, 'b>(id_help: &'a str) -> Vec<Arg<'a, 'b>> {
+    let mut args = vec![
+        Arg::with_name("ID")
+            .value_name("ID")
+            .takes_value(true)
+            .multiple(true)
+            .required_unless_one(&["TAG", "RNG", "GREP", "AUTO"])
+            .help(id_help),
+        Arg::with_name("YES")
+            .short("y")
+            .long("yes")
+            .help("Don't ask before changing more than one jot or deleting any"),
+    ];
+    args.extend(filter_args());
+    args
+}
+
+/// Pull the bulk arguments back out of the matches.
+fn selection<'a>(matches: &'a clap::ArgMatches) -> Result<commands::edit::Selection<'a>> {
+    Ok(commands::edit::Selection {
+        ids: matches
+            .values_of("ID")
+            .map(|ids| ids.collect())
+            .unwrap_or_default(),
+        query: commands::view::Query::from_matches(matches)?,
+        yes: matches.is_present("YES"),
+        archived: false,
+    })
+}
+
+/// `--color` can be given after any subcommand so look for the one closest to the end.
+fn color_choice<'a>(matches: &'a clap::ArgMatches) -> &'a str {
+    match matches.subcommand() {
+        (_, Some(sub_matches)) if sub_matches.is_present("COLOR") => color_choice(sub_matches),
+        _ => matches.value_of("COLOR").unwrap_or("auto"),
+    }
+}
+
+/// For commands that rewrite lots of jots at once.
+fn dry_run_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("DRY_RUN")
+        .long("dry-run")
+        .help("Show what would change without changing anything")
+}
+
+/// The arguments shared by all of the commands that write a new jot.
+fn capture_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("MESSAGE")
+            .value_name("MESSAGE")
+            .takes_value(true)
+            .multiple(true)
+            .conflicts_with("M")
+            .help("What to write, skips the editor. Use - to read from stdin"),
+        Arg::with_name("M")
+            .short("m")
+            .long("message")
+            .value_name("MESSAGE")
+            .takes_value(true)
+            .help("What to write, skips the editor. Use - to read from stdin"),
+        Arg::with_name("QUIET")
+            .short("q")
+            .long("quiet")
+            .help("Only print the id of the new jot"),
+        Arg::with_name("AT")
+            .long("at")
+            .value_name("DATE")
+            .takes_value(true)
+            .help("When this happened as a human date string, e.g. \"yesterday at 3pm\""),
+        Arg::with_name("TEMPLATE")
+            .short("T")
+            .long("template")
+            .value_name("TEMPLATE")
+            .takes_value(true)
+            .help("Start from a template in the config directory's templates folder"),
+    ]
+}
+
+/// Pull the capture arguments back out of the matches.
+fn capture_options(matches: &clap::ArgMatches) -> Result<commands::create::CaptureOptions> {
+    let message = matches
+        .values_of("MESSAGE")
+        .map(|words| words.collect::<Vec<_>>().join(" "))
+        .or_else(|| matches.value_of("M").map(|m| m.to_string()));
+    let at = matches
+        .value_of("AT")
+        .map(utils::parse_human_range)
+        .transpose()?
+        .map(|(start, _end)| start);
+    // Templates are filled in for when the jot is dated, not when it's written.
+    let template = match matches.value_of("TEMPLATE") {
+        Some(name) => Some(template::render(
+            &template::load(name)?,
+            at.unwrap_or_else(chrono::Local::now),
+            &std::env::current_dir()?,
+        )),
+        None => None,
+    };
+    Ok(commands::create::CaptureOptions {
+        message,
+        quiet: matches.is_present("QUIET"),
+        template,
+        at,
+    })
+}
+
 // New commands `jot` to make a note `jot list` to cat notes
 // maybe other shorthands? Anyways drop support for todos
 // Make tags be more condensed and colorized.
@@ -25,186 +220,560 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    let matches = App::new("jot")
-        .version("0.2.0")
-        .about("Jot down quick notes")
-        .subcommand(
-            SubCommand::with_name("cat")
-                .about("Dump out the entire journal")
-                .arg(
-                    Arg::with_name("RNG")
-                        .short("d")
-                        .long("date")
-                        .value_name("RNG")
-                        .takes_value(true)
-                        .multiple(true)
-                        .help("Filter by a human date range string"),
-                )
-                .arg(
-                    Arg::with_name("TAG")
-                        .short("t")
-                        .long("tag")
-                        .value_name("TAG")
-                        .takes_value(true)
-                        .multiple(true)
-                        .help("Filter by a tag"),
-                )
-                .arg(
-                    Arg::with_name("REVERSE")
-                        .short("r")
-                        .long("reverse")
-                        .help("Reverse the output"),
-                )
-                .arg(
-                    Arg::with_name("GREP")
-                        .short("g")
-                        .long("grep")
-                        .value_name("GREP")
-                        .takes_value(true)
-                        .multiple(true)
-                        .help("Filter by contents"),
-                ),
-        )
+    // The about text for our custom types needs to outlive the app.
+    let custom_types = config.types.clone();
+    let custom_abouts = custom_types
+        .iter()
+        .map(|jot_type| {
+            (
+                format!("Write a new {}", jot_type.name),
+                format!("View all {}", jot_type.plural),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let convert_types = vec![TODO, NOTE]
+        .into_iter()
+        .chain(custom_types.iter().map(|jot_type| jot_type.name.as_str()))
+        .collect::<Vec<_>>();
+
+    // The built in commands, custom types are checked against these so they
+    // can't hide one.
+    let built_ins = vec![
+        SubCommand::with_name("cat")
+            .about("Dump out the entire journal")
+            .args(&view_args_with_date(date_arg().multiple(true))),
+        SubCommand::with_name(PINNED)
+            .about("View all pinned jots")
+            .args(&view_args()),
         // TODO: this command should be moved to the visualization sub commands like a -i flag
         // or something.
-        .subcommand(
-            SubCommand::with_name("search")
-                .about("Perform interactive fuzzy searching on the journal."),
-        )
-        .subcommand(SubCommand::with_name("tags").about("List all tags"))
-        .subcommand(SubCommand::with_name(TODO).about("Write a todo"))
-        .subcommand(
-            SubCommand::with_name(TODOS)
-                .about("View all todos")
-                .arg(
-                    Arg::with_name("TAG")
-                        .short("t")
-                        .long("tag")
-                        .value_name("TAG")
-                        .takes_value(true)
-                        .multiple(true)
-                        .help("Filter by a tag"),
-                )
-                .arg(
-                    Arg::with_name("RANGE")
-                        .short("d")
-                        .long("date")
-                        .value_name("RANGE")
-                        .takes_value(true)
-                        .multiple(false)
-                        .help("Filter by a human date range string"),
-                )
-                .arg(
-                    Arg::with_name("REVERSE")
-                        .short("r")
-                        .long("reverse")
-                        .help("Reverse the output"),
-                )
-                .arg(
-                    Arg::with_name("GREP")
-                        .short("g")
-                        .long("grep")
-                        .value_name("GREP")
-                        .takes_value(true)
-                        .multiple(true)
-                        .help("Filter by contents"),
-                ),
-        )
-        .subcommand(SubCommand::with_name(NOTE).about("Write a note"))
-        .subcommand(
-            SubCommand::with_name(NOTES)
-                .about("View all notes")
-                .arg(
-                    Arg::with_name("TAG")
-                        .short("t")
-                        .long("tag")
-                        .value_name("TAG")
-                        .takes_value(true)
-                        .multiple(true)
-                        .help("Filter by a tag"),
-                )
-                .arg(
-                    Arg::with_name("RANGE")
-                        .short("d")
-                        .long("date")
-                        .value_name("RANGE")
-                        .takes_value(true)
-                        .multiple(false)
-                        .help("Filter by a human date range string"),
-                )
-                .arg(
-                    Arg::with_name("REVERSE")
-                        .short("r")
-                        .long("reverse")
-                        .help("Reverse the output"),
-                )
-                .arg(
-                    Arg::with_name("GREP")
-                        .short("g")
-                        .long("grep")
-                        .value_name("GREP")
-                        .takes_value(true)
-                        .multiple(true)
-                        .help("Filter by contents"),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("edit")
-                .about("Edit the contents of a note/todo")
-                .arg(
-                    Arg::with_name("ID")
-                        .value_name("ID")
-                        .takes_value(true)
-                        .help("The id of the note you wish to edit"),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("complete")
-                .about("Complete a todo")
-                .arg(
-                    Arg::with_name("ID")
-                        .value_name("ID")
-                        .takes_value(true)
-                        .help("The id of the todo you wish to complete"),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("delete")
-                .about("Complete a todo")
-                .arg(
-                    Arg::with_name("ID")
-                        .value_name("ID")
-                        .takes_value(true)
-                        .help("The id of the todo you wish to delete"),
-                ),
+        SubCommand::with_name("search")
+            .about("Perform interactive fuzzy searching on the journal."),
+        SubCommand::with_name("tags")
+            .about("List all tags")
+            .arg(format_arg())
+            .subcommand(
+                SubCommand::with_name("rename")
+                    .about("Rename a tag in every jot")
+                    .arg(
+                        Arg::with_name("OLD")
+                            .value_name("OLD")
+                            .required(true)
+                            .help("The tag to rename"),
+                    )
+                    .arg(
+                        Arg::with_name("NEW")
+                            .value_name("NEW")
+                            .required(true)
+                            .help("What to call it now"),
+                    )
+                    .arg(dry_run_arg()),
+            )
+            .subcommand(
+                SubCommand::with_name("merge")
+                    .about("Merge several tags into one in every jot")
+                    .arg(
+                        Arg::with_name("TAGS")
+                            .value_name("TAGS")
+                            .multiple(true)
+                            .required(true)
+                            .help("The tags to merge"),
+                    )
+                    .arg(
+                        Arg::with_name("INTO")
+                            .long("into")
+                            .value_name("TAG")
+                            .takes_value(true)
+                            .required(true)
+                            .help("The tag they all become"),
+                    )
+                    .arg(dry_run_arg()),
+            )
+            .subcommand(
+                SubCommand::with_name("remove")
+                    .about("Remove a tag from every jot")
+                    .arg(
+                        Arg::with_name("TAG")
+                            .value_name("TAG")
+                            .required(true)
+                            .help("The tag to remove"),
+                    )
+                    .arg(dry_run_arg()),
+            ),
+        SubCommand::with_name("retag")
+            .about("Apply the tag rules from the config to existing jots")
+            .arg(dry_run_arg()),
+        SubCommand::with_name(TODO)
+            .about("Write a todo")
+            .args(&capture_args())
+            .arg(
+                Arg::with_name("DUE")
+                    .long("due")
+                    .value_name("DATE")
+                    .takes_value(true)
+                    .help("When the todo is due as a human date string"),
+            )
+            .arg(
+                Arg::with_name("PRIORITY")
+                    .short("p")
+                    .long("priority")
+                    .value_name("PRIORITY")
+                    .takes_value(true)
+                    .possible_values(&["low", "medium", "high"])
+                    .help("The priority of the todo"),
+            )
+            .arg(
+                Arg::with_name("EVERY")
+                    .short("e")
+                    .long("every")
+                    .value_name("RULE")
+                    .takes_value(true)
+                    .help("Make the todo recur, e.g. \"weekly on monday\" or \"every 2 weeks\""),
+            ),
+        SubCommand::with_name(TODOS)
+            .about("View all todos")
+            .args(&view_args())
+            .arg(
+                Arg::with_name("SORT")
+                    .short("s")
+                    .long("sort")
+                    .value_name("SORT")
+                    .takes_value(true)
+                    .possible_values(&["due", "priority"])
+                    .help("Sort by due date or priority"),
+            )
+            .arg(
+                Arg::with_name("STATE")
+                    .long("state")
+                    .value_name("STATE")
+                    .takes_value(true)
+                    .multiple(true)
+                    .possible_values(&TODO_STATES)
+                    .help("Only show todos in these states"),
+            )
+            .arg(
+                Arg::with_name("OVERDUE")
+                    .long("overdue")
+                    .help("Only show todos that are past their due date"),
+            )
+            .arg(
+                Arg::with_name("DUE")
+                    .long("due")
+                    .value_name("RNG")
+                    .takes_value(true)
+                    .help("Only show todos due in a human date range string"),
+            ),
+        SubCommand::with_name(NOTE)
+            .about("Write a note")
+            .args(&capture_args()),
+        SubCommand::with_name(NOTES)
+            .about("View all notes")
+            .args(&view_args()),
+        SubCommand::with_name("today").about("Write in today's journal entry"),
+        SubCommand::with_name(DAY)
+            .about("Write in the journal entry for a day")
+            .arg(
+                Arg::with_name("DATE")
+                    .value_name("DATE")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The day as a human date string, e.g. yesterday"),
+            ),
+        SubCommand::with_name(DAYS)
+            .about("View all daily journal entries")
+            .args(&view_args()),
+        SubCommand::with_name("edit")
+            .about("Edit the contents of a note/todo")
+            .args(&bulk_args("The ids of the jots you wish to edit"))
+            .arg(
+                Arg::with_name("FULL")
+                    .long("full")
+                    .help("Edit the header (date, type, id...) along with the message"),
+            ),
+        SubCommand::with_name("complete")
+            .about("Complete a todo")
+            .args(&bulk_args("The ids of the todos you wish to complete")),
+        SubCommand::with_name("reopen")
+            .about("Mark a completed todo as not done")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the todo you wish to reopen"),
+            ),
+        SubCommand::with_name("pin")
+            .about("Pin a jot so it is shown first")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the jot you wish to pin"),
+            ),
+        SubCommand::with_name("unpin")
+            .about("Stop showing a jot first")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the jot you wish to unpin"),
+            ),
+        SubCommand::with_name("start")
+            .about("Start working on a todo")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the todo you are starting, this starts the clock"),
+            ),
+        SubCommand::with_name("stop")
+            .about("Stop the clock on a todo and put it back to open")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the todo you are taking a break from"),
+            ),
+        SubCommand::with_name("export")
+            .about("Export the journal somewhere else")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("site")
+                    .about("Render jots to a static HTML site with tag and date indexes")
+                    .arg(
+                        Arg::with_name("DIR")
+                            .value_name("DIR")
+                            .required(true)
+                            .help("The directory to write the site to"),
+                    )
+                    .arg(
+                        Arg::with_name("MARKDOWN")
+                            .long("markdown")
+                            .help("Write a folder of Markdown notes instead of HTML"),
+                    )
+                    .arg(
+                        Arg::with_name("INCLUDE_ARCHIVED")
+                            .long("include-archived")
+                            .help("Export archived jots too"),
+                    )
+                    .args(&filter_args()),
+            ),
+        SubCommand::with_name("timesheet")
+            .about("Report time worked on todos by tag")
+            .arg(
+                Arg::with_name("RNG")
+                    .short("d")
+                    .long("date")
+                    .value_name("RNG")
+                    .takes_value(true)
+                    .default_value("this week")
+                    .help("The human date range string to report on"),
+            ),
+        SubCommand::with_name("block")
+            .about("Mark a todo as blocked")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the todo that is blocked"),
+            )
+            .arg(
+                Arg::with_name("REASON")
+                    .value_name("REASON")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Why the todo is blocked"),
+            ),
+        SubCommand::with_name("wait")
+            .about("Mark a todo as waiting on someone else")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the todo that is waiting"),
+            ),
+        SubCommand::with_name("cancel").about("Cancel a todo").arg(
+            Arg::with_name("ID")
+                .value_name("ID")
+                .takes_value(true)
+                .required(true)
+                .help("The id of the todo you wish to cancel"),
+        ),
+        SubCommand::with_name("convert")
+            .about("Change the type of a note/todo, e.g. turn a note into a todo")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the note/todo you wish to convert"),
+            )
+            .arg(
+                Arg::with_name("TO")
+                    .long("to")
+                    .value_name("TYPE")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_values(&convert_types)
+                    .help("What to convert it to"),
+            ),
+        SubCommand::with_name("check")
+            .about("Check off an item in a note/todo checklist")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the note/todo with the checklist"),
+            )
+            .arg(
+                Arg::with_name("ITEM")
+                    .value_name("ITEM")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Which item to check off, starting at 1"),
+            ),
+        SubCommand::with_name("append")
+            .about("Add a timestamped line to a jot without opening the editor")
+            .arg(
+                Arg::with_name("ID")
+                    .value_name("ID")
+                    .takes_value(true)
+                    .required(true)
+                    .help("The id of the jot to add to"),
+            )
+            .arg(
+                Arg::with_name("TEXT")
+                    .value_name("TEXT")
+                    .takes_value(true)
+                    .multiple(true)
+                    .required(true)
+                    .help("What to add. Use - to read from stdin"),
+            ),
+        SubCommand::with_name(ARCHIVE)
+            .about("Move jots out of the way into the archive")
+            .args(&bulk_args("The ids of the jots you wish to archive"))
+            .arg(
+                Arg::with_name("AUTO")
+                    .long("auto")
+                    .conflicts_with_all(&["ID", "TAG", "RNG", "GREP"])
+                    .help("Archive whatever the retention rules in the config say to"),
+            )
+            .arg(dry_run_arg().requires("AUTO")),
+        SubCommand::with_name("unarchive")
+            .about("Move jots back out of the archive")
+            .args(&bulk_args("The ids of the jots you wish to unarchive")),
+        SubCommand::with_name("delete")
+            .about("Delete jots")
+            .args(&bulk_args("The ids of the jots you wish to delete")),
+    ];
+    config::validate_type_commands(&custom_types, &built_ins)?;
+
+    let mut app = App::new("jot")
+        .version("0.2.0")
+        .about("Jot down quick notes")
+        .arg(
+            Arg::with_name("COLOR")
+                .long("color")
+                .value_name("WHEN")
+                .takes_value(true)
+                .global(true)
+                .possible_values(&output::COLOR_CHOICES)
+                .help("Color the output always, never or only on a terminal (the default)"),
         )
-        .get_matches();
+        .subcommands(built_ins);
 
-    if let Some(_matches) = matches.subcommand_matches(NOTE) {
-        let previous_uuids = commands::view::get_all_uuids(config.clone())
-            .unwrap_or(std::collections::HashSet::new());
-        return commands::create::create_note_command(config, &previous_uuids);
+    for (jot_type, (create_about, view_about)) in custom_types.iter().zip(&custom_abouts) {
+        app = app
+            .subcommand(
+                SubCommand::with_name(&jot_type.name)
+                    .about(create_about.as_str())
+                    .args(&capture_args()),
+            )
+            .subcommand(
+                SubCommand::with_name(&jot_type.plural)
+                    .about(view_about.as_str())
+                    .args(&view_args()),
+            );
     }
 
-    if let Some(_matches) = matches.subcommand_matches(TODO) {
-        let previous_uuids = commands::view::get_all_uuids(config.clone())
-            .unwrap_or(std::collections::HashSet::new());
-        return commands::create::create_todo_command(config, &previous_uuids);
+    let matches = app.get_matches();
+    output::init(color_choice(&matches), config.wrap_width)?;
+
+    for jot_type in &custom_types {
+        if let Some(matches) = matches.subcommand_matches(&jot_type.name) {
+            let previous_uuids = commands::view::get_all_uuids(config.clone()).unwrap_or_default();
+            return commands::create::create_custom_command(
+                config,
+                &previous_uuids,
+                jot_type.clone(),
+                capture_options(matches)?,
+            );
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches(NOTE) {
+        let previous_uuids = commands::view::get_all_uuids(config.clone()).unwrap_or_default();
+        return commands::create::create_note_command(
+            config,
+            &previous_uuids,
+            capture_options(matches)?,
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches(TODO) {
+        let due = matches
+            .value_of("DUE")
+            .map(utils::parse_human_due)
+            .transpose()?
+            // Something due "friday" is due by the end of friday.
+            .map(|(_start, end)| end);
+        let priority = matches.value_of("PRIORITY").and_then(Priority::from_string);
+        let recurrence = matches
+            .value_of("EVERY")
+            .map(recurrence::Recurrence::from_string)
+            .transpose()?;
+        let previous_uuids = commands::view::get_all_uuids(config.clone()).unwrap_or_default();
+        return commands::create::create_todo_command(
+            config,
+            &previous_uuids,
+            due,
+            priority,
+            recurrence,
+            capture_options(matches)?,
+        );
+    }
+
+    if let Some(_matches) = matches.subcommand_matches("today") {
+        let previous_uuids = commands::view::get_all_uuids(config.clone()).unwrap_or_default();
+        return commands::day::day_command(config, "today", &previous_uuids);
+    }
+
+    if let Some(matches) = matches.subcommand_matches(DAY) {
+        let date = matches.value_of("DATE").unwrap();
+        let previous_uuids = commands::view::get_all_uuids(config.clone()).unwrap_or_default();
+        return commands::day::day_command(config, date, &previous_uuids);
     }
 
     if let Some(matches) = matches.subcommand_matches("edit") {
-        let id_or_uuid = matches.value_of("ID").unwrap();
-        return commands::edit::edit_jot_contents(config, id_or_uuid);
+        return commands::edit::edit_command(
+            config,
+            selection(matches)?,
+            matches.is_present("FULL"),
+        );
+    }
+
+    if let Some(matches) = matches.subcommand_matches(ARCHIVE) {
+        if matches.is_present("AUTO") {
+            return commands::archive::auto_archive_command(config, matches.is_present("DRY_RUN"));
+        }
+        return commands::archive::archive_command(config, selection(matches)?);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("unarchive") {
+        let selection = commands::edit::Selection {
+            archived: true,
+            ..selection(matches)?
+        };
+        return commands::archive::unarchive_command(config, selection);
     }
 
     if let Some(matches) = matches.subcommand_matches("delete") {
-        let id_or_uuid = matches.value_of("ID").unwrap();
-        return commands::edit::delete_jot(config, id_or_uuid);
+        return commands::edit::delete_command(config, selection(matches)?);
     }
 
     if let Some(matches) = matches.subcommand_matches("complete") {
+        return commands::edit::mark_todo_complete_command(config, selection(matches)?);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("reopen") {
         let id_or_uuid = matches.value_of("ID").unwrap();
-        return commands::edit::mark_todo_complete_command(config, id_or_uuid);
+        return commands::edit::reopen_todo_command(config, id_or_uuid);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("pin") {
+        let id_or_uuid = matches.value_of("ID").unwrap();
+        return commands::edit::set_pinned_command(config, id_or_uuid, true);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("unpin") {
+        let id_or_uuid = matches.value_of("ID").unwrap();
+        return commands::edit::set_pinned_command(config, id_or_uuid, false);
+    }
+
+    let state_sub_cmd = vec![
+        ("start", TODO_STATE_IN_PROGRESS),
+        ("block", TODO_STATE_BLOCKED),
+        ("wait", TODO_STATE_WAITING),
+        ("cancel", TODO_STATE_CANCELLED),
+    ]
+    .into_iter()
+    .find(|(cmd, _)| matches.subcommand_matches(cmd).is_some());
+    if let Some((state_cmd, state)) = state_sub_cmd {
+        let matches = matches.subcommand_matches(state_cmd).unwrap();
+        let id_or_uuid = matches.value_of("ID").unwrap();
+        let reason = matches.value_of("REASON");
+        return commands::edit::set_todo_state_command(config, id_or_uuid, state, reason);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("retag") {
+        return commands::edit::retag_command(config, matches.is_present("DRY_RUN"));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("stop") {
+        let id_or_uuid = matches.value_of("ID").unwrap();
+        return commands::edit::stop_todo_command(config, id_or_uuid);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export") {
+        if let Some(matches) = matches.subcommand_matches("site") {
+            let kind = if matches.is_present("MARKDOWN") {
+                commands::export::SiteKind::Markdown
+            } else {
+                commands::export::SiteKind::Html
+            };
+            return commands::export::export_site_command(
+                config,
+                std::path::Path::new(matches.value_of("DIR").unwrap()),
+                commands::view::Query::from_matches(matches)?,
+                matches.is_present("INCLUDE_ARCHIVED"),
+                kind,
+            );
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("timesheet") {
+        let range = matches.value_of("RNG").unwrap();
+        return commands::timesheet::timesheet_command(config, range);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("convert") {
+        let id_or_uuid = matches.value_of("ID").unwrap();
+        let to = matches.value_of("TO").unwrap();
+        return commands::edit::convert_jot_command(config, id_or_uuid, to);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("check") {
+        let id_or_uuid = matches.value_of("ID").unwrap();
+        let item = matches.value_of("ITEM").unwrap().parse::<usize>()?;
+        return commands::edit::check_item_command(config, id_or_uuid, item);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("append") {
+        let id_or_uuid = matches.value_of("ID").unwrap();
+        let text = matches
+            .values_of("TEXT")
+            .unwrap()
+            .collect::<Vec<_>>()
+            .join(" ");
+        return commands::edit::append_command(config, id_or_uuid, &text);
     }
 
     if let Some(_matches) = matches.subcommand_matches("search") {
@@ -212,15 +781,46 @@ fn main() -> Result<()> {
     }
 
     // Commands for displaying various note types.
-    let read_sub_cmd = vec![NOTES, TODOS, "cat"]
+    let read_sub_cmd = vec![NOTES, TODOS, DAYS, PINNED, "cat"]
         .into_iter()
+        .chain(custom_types.iter().map(|jot_type| jot_type.plural.as_str()))
         .find(|t| matches.subcommand_matches(t).is_some());
     if let Some(read_cmd) = read_sub_cmd {
         return commands::view::display(config, read_cmd, matches);
     }
 
-    if let Some(_matches) = matches.subcommand_matches("tags") {
-        return commands::tags::tags_command(config);
+    if let Some(matches) = matches.subcommand_matches("tags") {
+        use commands::tags::TagChange;
+        let (change, matches) = match matches.subcommand() {
+            ("rename", Some(matches)) => (
+                TagChange::Replace(
+                    vec![autotag::normalize_tag(matches.value_of("OLD").unwrap())?],
+                    autotag::normalize_tag(matches.value_of("NEW").unwrap())?,
+                ),
+                matches,
+            ),
+            ("merge", Some(matches)) => (
+                TagChange::Replace(
+                    matches
+                        .values_of("TAGS")
+                        .unwrap()
+                        .map(autotag::normalize_tag)
+                        .collect::<Result<Vec<_>>>()?,
+                    autotag::normalize_tag(matches.value_of("INTO").unwrap())?,
+                ),
+                matches,
+            ),
+            ("remove", Some(matches)) => (
+                TagChange::Remove(autotag::normalize_tag(matches.value_of("TAG").unwrap())?),
+                matches,
+            ),
+            _ => {
+                let format =
+                    format::Format::from_string(matches.value_of("FORMAT").unwrap_or("pretty"))?;
+                return commands::tags::tags_command(config, format);
+            }
+        };
+        return commands::tags::change_tags_command(config, change, matches.is_present("DRY_RUN"));
     }
 
     // matches.print_help();
@@ -0,0 +1,205 @@
+/// How output is shaped for wherever stdout goes, a terminal, a file or another program.
+use anyhow::{bail, Result};
+use regex::Regex;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+pub const COLOR_CHOICES: [&str; 3] = ["auto", "always", "never"];
+
+/// The width we wrap to when there's no terminal to ask.
+pub const DEFAULT_WRAP_WIDTH: usize = 80;
+
+/// Used when neither the config nor `$PAGER` say otherwise.
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Anything narrower than this and the terminal is probably lying about its size.
+pub const MIN_WRAP_WIDTH: usize = 20;
+
+static COLORS: AtomicBool = AtomicBool::new(true);
+static WRAP_WIDTH: AtomicUsize = AtomicUsize::new(DEFAULT_WRAP_WIDTH);
+
+lazy_static! {
+    static ref ANSI_RE: Regex = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+}
+
+/// Decide once, at startup, whether to use colors and how wide to wrap.
+/// `auto` only colors a terminal and honors `NO_COLOR` (https://no-color.org).
+pub fn init(color: &str, wrap_width: Option<usize>) -> Result<()> {
+    let colors = match color {
+        "always" => true,
+        "never" => false,
+        "auto" => {
+            let no_color = std::env::var_os("NO_COLOR")
+                .map(|value| !value.is_empty())
+                .unwrap_or(false);
+            !no_color && is_terminal()
+        }
+        _ => bail!(
+            "unknown color choice {:?}, use one of {}",
+            color,
+            COLOR_CHOICES.join(", ")
+        ),
+    };
+    COLORS.store(colors, Ordering::Relaxed);
+    WRAP_WIDTH.store(wrap_width.unwrap_or(DEFAULT_WRAP_WIDTH), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Whether stdout is a terminal, anything else gets plain text without boxes.
+pub fn is_terminal() -> bool {
+    console::Term::stdout().is_term()
+}
+
+/// How wide the output can be, the configured wrap width when there's no terminal.
+pub fn width() -> usize {
+    if is_terminal() {
+        if let Some((_height, width)) = console::Term::stdout().size_checked() {
+            if width as usize >= MIN_WRAP_WIDTH {
+                return width as usize;
+            }
+        }
+    }
+    WRAP_WIDTH.load(Ordering::Relaxed)
+}
+
+/// Strip the colors back out of some text if we aren't using them.
+pub fn paint(text: &str) -> String {
+    if COLORS.load(Ordering::Relaxed) {
+        text.to_string()
+    } else {
+        strip_colors(text)
+    }
+}
+
+/// Line rows up into columns, cutting the last column short so every row
+/// fits on one line.
+pub fn columns(rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = vec![];
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            let cell_width = console::measure_text_width(cell);
+            match widths.get_mut(i) {
+                Some(width) => *width = std::cmp::max(*width, cell_width),
+                None => widths.push(cell_width),
+            }
+        }
+    }
+
+    let mut text = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            if i + 1 == row.len() {
+                let used = console::measure_text_width(&line);
+                // Always leave some room for the last column, even on a tiny screen.
+                let room = std::cmp::max(width().saturating_sub(used), MIN_WRAP_WIDTH / 2);
+                line.push_str(&console::truncate_str(cell, room, "…"));
+            } else {
+                line.push_str(&console::pad_str(
+                    cell,
+                    widths[i],
+                    console::Alignment::Left,
+                    None,
+                ));
+                line.push_str("  ");
+            }
+        }
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    paint(&text)
+}
+
+#[test]
+fn test_columns() {
+    let rows = vec![
+        vec!["jc".to_string(), "todo".to_string(), "fresh".to_string()],
+        vec!["bcd".to_string(), "note".to_string(), "x".repeat(200)],
+    ];
+    let text = strip_colors(&columns(&rows));
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "jc   todo  fresh");
+    assert!(lines[1].starts_with("bcd  note  xxx"));
+    assert!(lines[1].ends_with('…'));
+    assert_eq!(console::measure_text_width(lines[1]), width());
+}
+
+/// The pager to use, `--no-pager` wins over the config which wins over
+/// `$PAGER`. An empty pager turns paging off, like git.
+pub fn pager_command(configured: Option<&str>, no_pager: bool) -> Option<String> {
+    if no_pager {
+        return None;
+    }
+    let pager = configured
+        .map(|pager| pager.to_string())
+        .or_else(|| std::env::var("PAGER").ok())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    if pager.trim().is_empty() {
+        None
+    } else {
+        Some(pager)
+    }
+}
+
+#[test]
+fn test_pager_command() {
+    assert_eq!(pager_command(Some("more"), false), Some("more".to_string()));
+    assert_eq!(pager_command(Some("more"), true), None);
+    assert_eq!(pager_command(Some(""), false), None);
+}
+
+/// Print some output, through the pager when it won't fit on the screen.
+pub fn page(text: &str, pager: Option<&str>) -> Result<()> {
+    let too_tall = console::Term::stdout()
+        .size_checked()
+        .map(|(height, _width)| text.lines().count() >= height as usize)
+        .unwrap_or(false);
+    let pager = match pager {
+        Some(pager) if too_tall && is_terminal() => pager,
+        _ => {
+            print!("{}", text);
+            return Ok(());
+        }
+    };
+
+    // Go through the shell so pagers with arguments work.
+    let mut child = match Command::new("sh")
+        .arg("-c")
+        .arg(pager)
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        // Output without a pager is better than no output.
+        Err(_) => {
+            print!("{}", text);
+            return Ok(());
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe, that's fine.
+        if let Err(err) = stdin.write_all(text.as_bytes()) {
+            if err.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(err.into());
+            }
+        }
+    }
+    let status = child.wait()?;
+    // The shell couldn't find the pager so it never showed anything.
+    if status.code() == Some(127) {
+        print!("{}", text);
+    }
+    Ok(())
+}
+
+fn strip_colors(text: &str) -> String {
+    ANSI_RE.replace_all(text, "").to_string()
+}
+
+#[test]
+fn test_strip_colors() {
+    use colorful::Colorful;
+    let text = format!("{} [{}]", "todo".magenta().bold(), "jc".cyan());
+    assert_eq!(strip_colors(&text), "todo [jc]");
+}
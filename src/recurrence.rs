@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Result};
+use chrono::prelude::*;
+use chrono::Duration;
+use regex::Regex;
+
+/// How often a recurring todo comes back around.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Recurrence {
+    /// Every n days.
+    Daily(u32),
+    /// Every n weeks, optionally landing on a specific weekday.
+    Weekly(u32, Option<Weekday>),
+    /// Every n months, optionally landing on a specific day of the month.
+    Monthly(u32, Option<u32>),
+}
+
+impl Recurrence {
+    /// Parse a human recurrence rule such as "daily", "weekly on monday",
+    /// "every 2 weeks" or "monthly on the 1st". Dashes are treated as spaces
+    /// so the same parser works on what we write out to the header.
+    pub fn from_string(i: &str) -> Result<Recurrence> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(
+                r"^(?:(daily|weekly|monthly)|every (?:(\d+) )?(day|week|month)s?|every (\w+))(?: on (?:the )?(\w+))?$"
+            )
+            .unwrap();
+        }
+        let normalized = i
+            .to_ascii_lowercase()
+            .replace('-', " ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let invalid = || {
+            anyhow!("invalid recurrence {:?}, try something like \"weekly on monday\" or \"every 2 weeks\"", i)
+        };
+        let caps = RE.captures(&normalized).ok_or_else(invalid)?;
+
+        let every = caps
+            .get(2)
+            .map(|n| n.as_str().parse::<u32>())
+            .transpose()?
+            .unwrap_or(1);
+        if every == 0 {
+            return Err(invalid());
+        }
+
+        let unit = caps
+            .get(1)
+            .map(|m| match m.as_str() {
+                "daily" => "day",
+                "weekly" => "week",
+                _ => "month",
+            })
+            .or_else(|| caps.get(3).map(|m| m.as_str()));
+        let on = caps.get(5).map(|m| m.as_str());
+
+        match (unit, caps.get(4), on) {
+            // "every monday"
+            (None, Some(weekday), None) => Ok(Recurrence::Weekly(
+                1,
+                Some(parse_weekday(weekday.as_str()).ok_or_else(invalid)?),
+            )),
+            (Some("day"), _, None) => Ok(Recurrence::Daily(every)),
+            (Some("week"), _, on) => Ok(Recurrence::Weekly(
+                every,
+                on.map(|on| parse_weekday(on).ok_or_else(invalid))
+                    .transpose()?,
+            )),
+            (Some("month"), _, on) => Ok(Recurrence::Monthly(
+                every,
+                on.map(|on| parse_day_of_month(on).ok_or_else(invalid))
+                    .transpose()?,
+            )),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The first day strictly after `day` that this rule lands on.
+    pub fn next_date(&self, day: NaiveDate) -> NaiveDate {
+        match *self {
+            Recurrence::Daily(every) => day + Duration::days(every as i64),
+            Recurrence::Weekly(every, None) => day + Duration::weeks(every as i64),
+            Recurrence::Weekly(every, Some(weekday)) => {
+                let mut next = day.succ();
+                while next.weekday() != weekday {
+                    next = next.succ();
+                }
+                next + Duration::weeks(every as i64 - 1)
+            }
+            Recurrence::Monthly(every, day_of_month) => {
+                let target = day_of_month.unwrap_or_else(|| day.day());
+                let this_month = clamped_date(day.year(), day.month(), target);
+                let first = if this_month > day {
+                    this_month
+                } else {
+                    add_months(day.year(), day.month(), 1, target)
+                };
+                add_months(first.year(), first.month(), every - 1, target)
+            }
+        }
+    }
+
+    /// Given when the last instance was due, figure out when the next one is due.
+    /// Due dates mark the end of the day they are due on, so we step the day
+    /// it was due on and keep the same time of day. Stepping in local calendar
+    /// days keeps that time put across daylight saving changes.
+    pub fn next_due(&self, due: DateTime<Local>) -> DateTime<Local> {
+        let due_day = (due - Duration::seconds(1)).date().naive_local();
+        let step = self.next_date(due_day) - due_day;
+        let next = (due.naive_local().date() + step).and_time(due.naive_local().time());
+        Local
+            .from_local_datetime(&next)
+            .earliest()
+            // The time was skipped by the clocks going forward.
+            .unwrap_or(due + step)
+    }
+}
+
+impl std::fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (base, on) = match *self {
+            Recurrence::Daily(1) => ("daily".to_string(), None),
+            Recurrence::Daily(every) => (format!("every {} days", every), None),
+            Recurrence::Weekly(1, on) => ("weekly".to_string(), on.map(weekday_name)),
+            Recurrence::Weekly(every, on) => {
+                (format!("every {} weeks", every), on.map(weekday_name))
+            }
+            Recurrence::Monthly(1, on) => ("monthly".to_string(), on.map(ordinal)),
+            Recurrence::Monthly(every, on) => (format!("every {} months", every), on.map(ordinal)),
+        };
+        match on {
+            Some(on) => write!(f, "{} on {}", base, on),
+            None => write!(f, "{}", base),
+        }
+    }
+}
+
+fn parse_weekday(i: &str) -> Option<Weekday> {
+    i.parse::<Weekday>().ok()
+}
+
+fn weekday_name(weekday: Weekday) -> String {
+    match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+    .to_string()
+}
+
+/// Parse things like "1st", "22nd" or just "15".
+fn parse_day_of_month(i: &str) -> Option<u32> {
+    let digits = i.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let day = digits.parse::<u32>().ok()?;
+    if (1..=31).contains(&day) && (digits == i || ordinal(day) == format!("the {}", i)) {
+        Some(day)
+    } else {
+        None
+    }
+}
+
+fn ordinal(day: u32) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("the {}{}", day, suffix)
+}
+
+/// Build a date, pulling the day back to the end of the month when needed
+/// (e.g. "monthly on the 31st" in February).
+fn clamped_date(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .unwrap()
+}
+
+fn add_months(year: i32, month: u32, months: u32, day: u32) -> NaiveDate {
+    let zero_based = month - 1 + months;
+    clamped_date(year + (zero_based / 12) as i32, zero_based % 12 + 1, day)
+}
+
+#[test]
+fn test_recurrence_from_string() {
+    assert_eq!(
+        Recurrence::from_string("daily").unwrap(),
+        Recurrence::Daily(1)
+    );
+    assert_eq!(
+        Recurrence::from_string("weekly on Monday").unwrap(),
+        Recurrence::Weekly(1, Some(Weekday::Mon))
+    );
+    assert_eq!(
+        Recurrence::from_string("every 2 weeks").unwrap(),
+        Recurrence::Weekly(2, None)
+    );
+    assert_eq!(
+        Recurrence::from_string("monthly on the 1st").unwrap(),
+        Recurrence::Monthly(1, Some(1))
+    );
+    assert_eq!(
+        Recurrence::from_string("every friday").unwrap(),
+        Recurrence::Weekly(1, Some(Weekday::Fri))
+    );
+    assert!(Recurrence::from_string("daily on monday").is_err());
+    assert!(Recurrence::from_string("every 0 days").is_err());
+    assert!(Recurrence::from_string("monthly on the 1nd").is_err());
+
+    // What we write to the header should parse back to the same rule.
+    for rule in &[
+        Recurrence::Daily(3),
+        Recurrence::Weekly(2, Some(Weekday::Thu)),
+        Recurrence::Monthly(1, Some(22)),
+    ] {
+        let header_value = rule.to_string().replace(' ', "-");
+        assert_eq!(Recurrence::from_string(&header_value).unwrap(), *rule);
+    }
+}
+
+#[test]
+fn test_recurrence_next_date() {
+    // 2020-04-13 is a Monday.
+    let monday = NaiveDate::from_ymd(2020, 4, 13);
+    assert_eq!(
+        Recurrence::Daily(1).next_date(monday),
+        NaiveDate::from_ymd(2020, 4, 14)
+    );
+    assert_eq!(
+        Recurrence::Weekly(1, Some(Weekday::Mon)).next_date(monday),
+        NaiveDate::from_ymd(2020, 4, 20)
+    );
+    assert_eq!(
+        Recurrence::Weekly(2, Some(Weekday::Wed)).next_date(monday),
+        NaiveDate::from_ymd(2020, 4, 22)
+    );
+    assert_eq!(
+        Recurrence::Monthly(1, Some(1)).next_date(monday),
+        NaiveDate::from_ymd(2020, 5, 1)
+    );
+    assert_eq!(
+        Recurrence::Monthly(1, Some(20)).next_date(monday),
+        NaiveDate::from_ymd(2020, 4, 20)
+    );
+    assert_eq!(
+        Recurrence::Monthly(1, None).next_date(NaiveDate::from_ymd(2020, 1, 31)),
+        NaiveDate::from_ymd(2020, 2, 29)
+    );
+    assert_eq!(
+        Recurrence::Monthly(2, Some(1)).next_date(NaiveDate::from_ymd(2020, 12, 1)),
+        NaiveDate::from_ymd(2021, 2, 1)
+    );
+}
+
+#[test]
+fn test_recurrence_next_due() {
+    // Due by the end of Monday the 13th, so the next one is due by the end of Tuesday.
+    let end_of_monday = Local.ymd(2020, 4, 14).and_hms(0, 0, 0);
+    assert_eq!(
+        Recurrence::Daily(1).next_due(end_of_monday),
+        Local.ymd(2020, 4, 15).and_hms(0, 0, 0)
+    );
+    let friday_at_five = Local.ymd(2020, 4, 17).and_hms(17, 0, 0);
+    assert_eq!(
+        Recurrence::Weekly(1, None).next_due(friday_at_five),
+        Local.ymd(2020, 4, 24).and_hms(17, 0, 0)
+    );
+    // Clocks went forward on March 8th in a lot of places, the time shouldn't move.
+    let before_dst = Local.ymd(2020, 3, 5).and_hms(9, 0, 0);
+    assert_eq!(
+        Recurrence::Weekly(1, None).next_due(before_dst),
+        Local.ymd(2020, 3, 12).and_hms(9, 0, 0)
+    );
+}
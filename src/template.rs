@@ -0,0 +1,46 @@
+/// Templates that new jots can start from, they live in the config directory.
+use anyhow::{bail, Context, Result};
+use chrono::prelude::*;
+use std::path::Path;
+
+use crate::config;
+
+/// Load a template by name from the templates directory, e.g. `meeting` will
+/// look for `templates/meeting` or `templates/meeting.md`.
+pub fn load(name: &str) -> Result<String> {
+    let dir = config::templates_dir().context("failed to get templates directory")?;
+    for file_name in &[name.to_string(), format!("{}.md", name)] {
+        let path = dir.join(file_name);
+        if path.is_file() {
+            return Ok(std::fs::read_to_string(path)?);
+        }
+    }
+
+    bail!(
+        "there is no template named {:?}, add one to {}",
+        name,
+        dir.to_str().unwrap_or_default()
+    )
+}
+
+/// Fill in the variables in a template.
+pub fn render(template: &str, now: DateTime<Local>, cwd: &Path) -> String {
+    template
+        .replace("{{date}}", &now.format("%Y-%m-%d").to_string())
+        .replace("{{time}}", &now.format("%H:%M").to_string())
+        .replace("{{weekday}}", &now.format("%A").to_string())
+        .replace("{{cwd}}", cwd.to_str().unwrap_or_default())
+}
+
+#[test]
+fn test_render() {
+    let now = Local.ymd(2020, 4, 13).and_hms(9, 30, 0);
+    assert_eq!(
+        render(
+            "# Standup {{date}} ({{weekday}} {{time}}) @meeting\n{{cwd}} {{unknown}}",
+            now,
+            Path::new("/src/payments")
+        ),
+        "# Standup 2020-04-13 (Monday 09:30) @meeting\n/src/payments {{unknown}}"
+    );
+}
@@ -1,3 +1,164 @@
+use anyhow::{anyhow, Result};
+use chrono::prelude::*;
+use regex::Regex;
+use std::io::Write;
+
+/// Parse a human date range string (e.g. "next friday") into a pair of local times.
+pub fn parse_human_range(st: &str) -> Result<(DateTime<Local>, DateTime<Local>)> {
+    to_local_range(st, parse_naive_range(st, Local::now().naive_local())?)
+}
+
+/// Like `parse_human_range` but for due dates, "friday" or "3pm" mean the
+/// next one rather than the last one.
+pub fn parse_human_due(st: &str) -> Result<(DateTime<Local>, DateTime<Local>)> {
+    to_local_range(st, parse_future_range(st, Local::now().naive_local())?)
+}
+
+lazy_static! {
+    // A day followed by a time without the "at" two_timer wants in between.
+    static ref DAY_TIME_RE: Regex =
+        Regex::new(r"(?i)^(.*\S)\s+(\d{1,2}(?::\d{2})?\s*(?:[ap]\.?m\.?)?|noon|midnight)$")
+            .unwrap();
+}
+
+fn parse_naive_range(st: &str, now: NaiveDateTime) -> Result<(NaiveDateTime, NaiveDateTime)> {
+    let config = || Some(two_timer::Config::new().now(now));
+    two_timer::parse(st, config())
+        .or_else(|err| match DAY_TIME_RE.captures(st) {
+            Some(caps) if !caps[1].to_ascii_lowercase().ends_with(" at") => {
+                two_timer::parse(&format!("{} at {}", &caps[1], &caps[2]), config())
+            }
+            _ => Err(err),
+        })
+        .map(|(start, end, _)| (start, end))
+        .map_err(|_| anyhow!("failed to parse human time {:?}", st))
+}
+
+#[test]
+fn test_parse_naive_range() {
+    let now = NaiveDate::from_ymd(2026, 10, 19).and_hms(9, 0, 0);
+    let start = |st: &str| parse_naive_range(st, now).unwrap().0;
+    let yesterday_3pm = NaiveDate::from_ymd(2026, 10, 18).and_hms(15, 0, 0);
+    assert_eq!(start("yesterday at 3pm"), yesterday_3pm);
+    assert_eq!(start("yesterday 3pm"), yesterday_3pm);
+    assert_eq!(start("yesterday 15:00"), yesterday_3pm);
+    assert_eq!(
+        start("friday 9:30am"),
+        NaiveDate::from_ymd(2026, 10, 16).and_hms(9, 30, 0)
+    );
+    assert!(parse_naive_range("yesterday at 3xm", now).is_err());
+}
+
+/// two_timer always picks the last friday (or 3pm, or march) before now. When
+/// that's already over we look again from a bit later until we find the next
+/// one, unless the phrase asked for the past on purpose.
+fn parse_future_range(st: &str, now: NaiveDateTime) -> Result<(NaiveDateTime, NaiveDateTime)> {
+    let range = parse_naive_range(st, now)?;
+    let lower = st.to_ascii_lowercase();
+    let wants_past = ["yesterday", "last", "ago", "before"]
+        .iter()
+        .any(|word| lower.contains(word));
+    if range.1 > now || wants_past {
+        return Ok(range);
+    }
+    for ahead in &[
+        chrono::Duration::days(1),
+        chrono::Duration::days(7),
+        chrono::Duration::days(31),
+        chrono::Duration::days(366),
+    ] {
+        let later = parse_naive_range(st, now + *ahead)?;
+        if later.1 > now {
+            return Ok(later);
+        }
+    }
+    Ok(range)
+}
+
+#[test]
+fn test_parse_future_range() {
+    // A monday morning.
+    let now = NaiveDate::from_ymd(2026, 10, 19).and_hms(9, 0, 0);
+    let start = |st: &str| parse_future_range(st, now).unwrap().0;
+    assert_eq!(
+        start("friday"),
+        NaiveDate::from_ymd(2026, 10, 23).and_hms(0, 0, 0)
+    );
+    assert_eq!(
+        start("monday"),
+        NaiveDate::from_ymd(2026, 10, 19).and_hms(0, 0, 0)
+    );
+    assert_eq!(
+        start("3pm"),
+        NaiveDate::from_ymd(2026, 10, 19).and_hms(15, 0, 0)
+    );
+    assert_eq!(
+        start("8am"),
+        NaiveDate::from_ymd(2026, 10, 20).and_hms(8, 0, 0)
+    );
+    assert_eq!(
+        start("march"),
+        NaiveDate::from_ymd(2027, 3, 1).and_hms(0, 0, 0)
+    );
+    assert_eq!(
+        start("yesterday"),
+        NaiveDate::from_ymd(2026, 10, 18).and_hms(0, 0, 0)
+    );
+    assert_eq!(
+        start("last friday"),
+        NaiveDate::from_ymd(2026, 10, 16).and_hms(0, 0, 0)
+    );
+    assert_eq!(
+        start("2026-10-01"),
+        NaiveDate::from_ymd(2026, 10, 1).and_hms(0, 0, 0)
+    );
+}
+
+fn to_local_range(
+    st: &str,
+    (start, end): (NaiveDateTime, NaiveDateTime),
+) -> Result<(DateTime<Local>, DateTime<Local>)> {
+    let to_local = |naive: NaiveDateTime| {
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .ok_or_else(|| anyhow!("{:?} is not a valid local time", st))
+    };
+
+    Ok((to_local(start)?, to_local(end)?))
+}
+
+/// Parse an age like "90 days" or "2 years" into a duration, months are
+/// counted as 30 days and years as 365.
+pub fn parse_age(st: &str) -> Result<chrono::Duration> {
+    let invalid = || anyhow!("invalid age {:?}, try something like \"90 days\"", st);
+    let mut parts = st.split_whitespace();
+    let amount = parts
+        .next()
+        .and_then(|amount| amount.parse::<i64>().ok())
+        .ok_or_else(invalid)?;
+    let days = match parts.next().map(|unit| unit.trim_end_matches('s')) {
+        Some("day") => 1,
+        Some("week") => 7,
+        Some("month") => 30,
+        Some("year") => 365,
+        _ => return Err(invalid()),
+    };
+    if parts.next().is_some() || amount < 0 {
+        return Err(invalid());
+    }
+    Ok(chrono::Duration::days(amount * days))
+}
+
+#[test]
+fn test_parse_age() {
+    assert_eq!(parse_age("90 days").unwrap(), chrono::Duration::days(90));
+    assert_eq!(parse_age("1 year").unwrap(), chrono::Duration::days(365));
+    assert_eq!(parse_age("2  weeks").unwrap(), chrono::Duration::days(14));
+    assert!(parse_age("ninety days").is_err());
+    assert!(parse_age("90 fortnights").is_err());
+}
+
 /// Given a duration, return a tuple of (scalar, time-unit).
 /// This function attempts to round far away times to the nearest large
 /// unit (naively implemented so it doesn't exactly behave that way).
@@ -36,12 +197,49 @@ fn test_pretty_duration() {
     assert_eq!(pretty_duration(chrono::Duration::days(365)), (52, "week"));
 }
 
+/// Format time spent working on something in hours and minutes, e.g. "2 hours 5 minutes".
+pub fn pretty_work_duration(duration: chrono::Duration) -> String {
+    let hours = duration.num_hours();
+    let minutes = duration.num_minutes() - hours * 60;
+    let minutes_str = format!("{} {}", minutes, pluralize_time_unit(minutes, "minute"));
+    if hours > 0 {
+        format!(
+            "{} {} {}",
+            hours,
+            pluralize_time_unit(hours, "hour"),
+            minutes_str
+        )
+    } else {
+        minutes_str
+    }
+}
+
+#[test]
+fn test_pretty_work_duration() {
+    assert_eq!(
+        pretty_work_duration(chrono::Duration::seconds(59)),
+        "0 minutes"
+    );
+    assert_eq!(
+        pretty_work_duration(chrono::Duration::minutes(1)),
+        "1 minute"
+    );
+    assert_eq!(
+        pretty_work_duration(chrono::Duration::minutes(125)),
+        "2 hours 5 minutes"
+    );
+    assert_eq!(
+        pretty_work_duration(chrono::Duration::hours(26)),
+        "26 hours 0 minutes"
+    );
+}
+
 /// Pluralize words e.g. Hour => Hours, etc.
 pub fn pluralize_time_unit(amount: i64, time_unit: &str) -> String {
     if amount == 1 {
         return time_unit.to_string();
     }
-    return format!("{}s", time_unit);
+    format!("{}s", time_unit)
 }
 
 #[test]
@@ -66,15 +264,52 @@ fn test_count_real_chars() {
     assert_eq!(count_real_chars("🖋️"), Some(1)); // TODO: This is incorrect I think?
 }
 
+/// How many single character insertions, deletions or substitutions it takes
+/// to turn one string into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("@meeting", "@meeting"), 0);
+    assert_eq!(edit_distance("@meetng", "@meeting"), 1);
+    assert_eq!(edit_distance("@meeitng", "@meeting"), 2);
+    assert_eq!(edit_distance("", "abc"), 3);
+}
+
+/// Ask the user a yes or no question, anything but yes counts as no.
+pub fn confirm(question: &str) -> Result<bool> {
+    eprint!("{} [y/N] ", question);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer)? == 0 {
+        // Nothing to read so finish the prompt's line for them.
+        eprintln!();
+    }
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
 /// We use this function to attempt to format messages into smaller terminals.
 /// We will also render newlines similarly to how markdown does it.
 pub fn break_apart_long_string(st: &str) -> String {
-    let term = console::Term::stdout();
-    let (_height, width) = term.size();
-
-    let ideal_split_point = width - 4;
+    // Leave room for the box drawn around the message.
+    let ideal_split_point = crate::output::width().saturating_sub(4);
 
-    format!("{}", textwrap::fill(st, ideal_split_point as usize))
+    textwrap::fill(st, ideal_split_point)
 }
 
 const BASE: u32 = 21;
@@ -118,7 +353,7 @@ pub fn generate_new_uuid(previous_uuids: &std::collections::HashSet<String>) ->
         BASE_4
     } else {
         // Wow you have a lot of notes, have the entire bitspace
-        std::u32::MAX
+        u32::MAX
     };
 
     let mut n = rand::random::<u32>() % pool_to_draw_from;
@@ -129,7 +364,7 @@ pub fn generate_new_uuid(previous_uuids: &std::collections::HashSet<String>) ->
         }
 
         // Increase us but keep us inside this pool of candidates.
-        n = n + 1 % pool_to_draw_from;
+        n = (n + 1) % pool_to_draw_from;
     }
 }
 
@@ -141,13 +376,13 @@ fn num_to_string_id(num: u32) -> String {
         let base_26_digit = rem % BASE;
         out.push(LETTERS[base_26_digit as usize]);
 
-        rem = rem / BASE;
+        rem /= BASE;
         if rem == 0 {
             break;
         }
     }
 
-    return out;
+    out
 }
 
 #[test]